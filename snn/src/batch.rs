@@ -0,0 +1,320 @@
+//! Many trials of one network ticked together.
+//!
+//! A [`Batch`] runs independent copies of a model over its shared
+//! [`Topology`]. The state of all trials is interleaved, trial `b` of neuron
+//! `i` at index `i * trials + b` and likewise for synapses, so one pass over
+//! the synapses serves every trial and the trials of a neuron share cache
+//! lines. Each trial sees the same deliveries in the same order as a lone
+//! [`Model`], so its spikes and potentials are bit-identical.
+//!
+//! A batch starts from [`SimState`]s, one per trial, and hands each trial back
+//! as a `SimState` with [`state`](Batch::state), so a run can move between a
+//! batch and lone models at any tick.
+//!
+//! Batches have no plasticity, stimuli or monitors: feed them with
+//! [`add_input`](Batch::add_input) and read them after each tick. A model
+//! that uses any of these behaves differently on its own than in a batch.
+
+use std::sync::Arc;
+
+use crate::{Lif, Model, NeuronModel, SimState, Topology};
+
+/// Independent trials of one model, ticked together.
+///
+/// Trial `b` matches a lone model in the same state that gets the same
+/// inputs, as long as the model learns nothing and has no stimuli.
+pub struct Batch<N: NeuronModel = Lif> {
+    topology: Arc<Topology>,
+    trials: usize,
+    /// Neuron dynamics of every trial, interleaved
+    neurons: N,
+    spiked: Vec<bool>,
+    input: Vec<f32>,
+    /// Constant current per neuron, the same in every trial
+    bias: Vec<f32>,
+    /// Spikes in flight per synapse and trial, interleaved
+    in_flight: Vec<u32>,
+    /// Ring buffer of (synapse, trial) deliveries, as in the model
+    pending: Vec<Vec<(u32, u32)>>,
+    time: u64,
+}
+
+impl<N: NeuronModel + Clone> Batch<N> {
+    /// `trials` copies of `model` in its current state, with its bias.
+    ///
+    /// The learning rule, stimuli and monitors of the model are not taken over.
+    pub fn new(model: &Model<N>, trials: usize) -> Self {
+        let mut batch = Self::from_states(
+            model.topology().clone(),
+            vec![model.state().clone(); trials],
+        );
+        for i in 0..batch.neuron_count() as u32 {
+            batch.set_bias(i, model.bias(i));
+        }
+        batch
+    }
+
+    /// One trial per state, all over `topology` and without bias.
+    ///
+    /// Panics without states, if a state does not fit the topology, or if the
+    /// states are at different ticks.
+    pub fn from_states(topology: Arc<Topology>, states: Vec<SimState<N>>) -> Self {
+        assert!(!states.is_empty(), "a batch needs at least one trial");
+        assert!(
+            states.iter().all(|state| state.fits(&topology)),
+            "state does not fit the topology of the batch"
+        );
+        let time = states[0].time;
+        assert!(
+            states.iter().all(|state| state.time == time),
+            "trials of a batch must be at the same tick"
+        );
+        let trials = states.len();
+        let (n, e) = (topology.neuron_count(), topology.synapse_count());
+
+        let mut neurons = states[0].neurons.clone();
+        neurons.truncate(0);
+        let mut spiked = Vec::with_capacity(n * trials);
+        let mut input = Vec::with_capacity(n * trials);
+        for i in 0..n {
+            for state in &states {
+                neurons.push_copy(&state.neurons, i);
+                spiked.push(state.spiked[i]);
+                input.push(state.input[i]);
+            }
+        }
+        let in_flight = (0..e)
+            .flat_map(|j| states.iter().map(move |state| state.in_flight[j]))
+            .collect();
+        // Each trial keeps the delivery order of its own ring
+        let pending = (0..topology.max_delay() as usize + 1)
+            .map(|slot| {
+                states
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(b, state)| state.pending[slot].iter().map(move |&j| (j, b as u32)))
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            trials,
+            neurons,
+            spiked,
+            input,
+            bias: vec![0.0; n],
+            in_flight,
+            pending,
+            time,
+            topology,
+        }
+    }
+
+    /// State of `trial`, to continue it in a [`Model`] with
+    /// [`set_state`](Model::set_state) or in another batch.
+    pub fn state(&self, trial: usize) -> SimState<N> {
+        let n = self.neuron_count();
+        let mut neurons = self.neurons.clone();
+        neurons.truncate(0);
+        for i in 0..n as u32 {
+            neurons.push_copy(&self.neurons, self.index(trial, i));
+        }
+        let b = self.trials;
+        SimState {
+            neurons,
+            spiked: (0..n).map(|i| self.spiked[i * b + trial]).collect(),
+            input: (0..n).map(|i| self.input[i * b + trial]).collect(),
+            in_flight: (0..self.topology.synapse_count())
+                .map(|j| self.in_flight[j * b + trial])
+                .collect(),
+            pending: self
+                .pending
+                .iter()
+                .map(|slot| {
+                    slot.iter()
+                        .filter(|&&(_, t)| t as usize == trial)
+                        .map(|&(j, _)| j)
+                        .collect()
+                })
+                .collect(),
+            time: self.time,
+        }
+    }
+}
+
+impl<N: NeuronModel> Batch<N> {
+    pub fn trials(&self) -> usize {
+        self.trials
+    }
+
+    pub fn neuron_count(&self) -> usize {
+        self.topology.neuron_count()
+    }
+
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    pub fn topology(&self) -> &Arc<Topology> {
+        &self.topology
+    }
+
+    fn index(&self, trial: usize, neuron: u32) -> usize {
+        assert!(trial < self.trials, "batch has no trial {trial}");
+        neuron as usize * self.trials + trial
+    }
+
+    /// Constant current into `neuron` on every tick, in every trial.
+    pub fn set_bias(&mut self, neuron: u32, current: f32) {
+        self.bias[neuron as usize] = current;
+    }
+
+    /// Add `current` to the input of `neuron` in `trial` for the next tick only.
+    pub fn add_input(&mut self, trial: usize, neuron: u32, current: f32) {
+        let k = self.index(trial, neuron);
+        self.input[k] += current;
+    }
+
+    /// Membrane potential of `neuron` in `trial`.
+    pub fn v(&self, trial: usize, neuron: u32) -> f32 {
+        self.neurons.v()[self.index(trial, neuron)]
+    }
+
+    /// Whether `neuron` spiked in `trial` on the latest tick.
+    pub fn spiked(&self, trial: usize, neuron: u32) -> bool {
+        self.spiked[self.index(trial, neuron)]
+    }
+
+    /// Neurons that spiked in `trial` on the latest tick, in index order.
+    pub fn spikes(&self, trial: usize) -> Vec<u32> {
+        (0..self.neuron_count() as u32)
+            .filter(|&i| self.spiked(trial, i))
+            .collect()
+    }
+
+    /// Advance every trial by one tick.
+    pub fn tick(&mut self) {
+        self.time += 1;
+        let b = self.trials;
+
+        // 1) Reset spiked neurons, integrate the rest under external currents
+        for k in 0..self.spiked.len() {
+            if self.spiked[k] {
+                self.neurons.reset(k);
+                self.spiked[k] = false;
+            } else {
+                self.neurons.integrate(k, self.input[k] + self.bias[k / b]);
+            }
+        }
+        self.input.fill(0.0);
+
+        // 2) Deliver pending synapses due this tick
+        let ring_len = self.pending.len() as u64;
+        let slot = (self.time % ring_len) as usize;
+        let mut due = std::mem::take(&mut self.pending[slot]);
+        for &(j, trial) in &due {
+            let (j, trial) = (j as usize, trial as usize);
+            let recv = self.topology.receiver[j] as usize;
+            self.neurons
+                .receive(recv * b + trial, self.topology.weight[j]);
+            self.in_flight[j * b + trial] -= 1;
+        }
+        due.clear();
+        self.pending[slot] = due;
+
+        // 3) Update spikes and schedule deliveries, per trial in the order of a lone model
+        for k in 0..self.spiked.len() {
+            if !self.neurons.spike(k) {
+                continue;
+            }
+            self.spiked[k] = true;
+            let (i, trial) = (k / b, k % b);
+            let start = self.topology.out_offset[i] as usize;
+            let end = self.topology.out_offset[i + 1] as usize;
+            for j in start..end {
+                let arrival = ((self.time + self.topology.delay[j] as u64) % ring_len) as usize;
+                self.pending[arrival].push((j as u32, trial as u32));
+                self.in_flight[j * b + trial] += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Batch;
+    use crate::{Model, ModelBuilder, NeuronId, NeuronParams};
+
+    /// A ring with skips and delays of 1 to 5 ticks, driven for a while so
+    /// potentials and spikes in flight are away from rest.
+    fn warmed_up() -> Model {
+        let n = 60;
+        let mut builder = ModelBuilder::new();
+        builder.add_neurons(n, NeuronParams::default());
+        for i in 0..n as u32 {
+            let delay = 1 + (i % 5) as u16;
+            builder.connect_delayed(NeuronId(i), NeuronId((i + 1) % 60), 0.6, delay);
+            builder.connect_delayed(NeuronId(i), NeuronId((i + 7) % 60), -0.2, delay + 1);
+        }
+        let mut model = builder.build();
+        model.set_bias(5, 0.2);
+        for t in 0..40 {
+            model.add_input(t * 11 % 60, 1.5);
+            model.tick();
+        }
+        model
+    }
+
+    /// Tick `batch` and `lone` with the same per-trial inputs, comparing bits.
+    /// Returns the number of spikes.
+    fn run_both(batch: &mut Batch, lone: &mut [Model], ticks: u32) -> usize {
+        let mut spikes = 0;
+        for t in 0..ticks {
+            for (b, model) in lone.iter_mut().enumerate() {
+                let neuron = (t * 7 + b as u32 * 13) % 60;
+                if t % (3 + b as u32) == 0 {
+                    model.add_input(neuron, 1.5);
+                    batch.add_input(b, neuron, 1.5);
+                }
+            }
+            batch.tick();
+            for (b, model) in lone.iter_mut().enumerate() {
+                model.tick();
+                for i in 0..60 {
+                    assert_eq!(model.get_charge(i).to_bits(), batch.v(b, i).to_bits());
+                    assert_eq!(model.spiked()[i as usize], batch.spiked(b, i));
+                }
+                spikes += batch.spikes(b).len();
+            }
+        }
+        spikes
+    }
+
+    /// Every trial of a batch is bit-identical to a lone model with the same
+    /// inputs, and trials move between batches and lone models mid-run.
+    #[test]
+    fn trials_match_lone_models() {
+        let model = warmed_up();
+        assert!(model.state().in_flight().iter().any(|&k| k > 0));
+        let trials = 3;
+        let mut batch = Batch::new(&model, trials);
+        let mut lone: Vec<Model> = (0..trials).map(|_| model.fork()).collect();
+        assert!(run_both(&mut batch, &mut lone, 120) > 100);
+
+        // Lone models continue from the batch
+        for (b, model) in lone.iter_mut().enumerate() {
+            model.set_state(batch.state(b));
+        }
+        assert!(run_both(&mut batch, &mut lone, 60) > 50);
+
+        // A new batch continues from the lone models
+        let states = lone.iter().map(|m| m.state().clone()).collect();
+        let mut batch = Batch::from_states(model.topology().clone(), states);
+        batch.set_bias(5, 0.2);
+        assert_eq!(batch.time(), lone[0].time());
+        assert!(run_both(&mut batch, &mut lone, 120) > 100);
+        for (b, model) in lone.iter().enumerate() {
+            assert_eq!(batch.state(b).in_flight(), model.state().in_flight());
+        }
+    }
+}
@@ -0,0 +1,520 @@
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use super::{DEFAULT_SYNAPSE_DELAY, Error, Lif, LifParams, Model, NeuronKind, NeuronModel};
+use crate::population::{Connectivity, Population, Projection};
+use crate::spatial::{DistanceDelay, DistanceProfile, DistanceWeight, distance};
+
+/// Index of a neuron inside a [`Model`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NeuronId(pub u32);
+
+impl NeuronId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl From<u32> for NeuronId {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+impl From<NeuronId> for u32 {
+    fn from(value: NeuronId) -> Self {
+        value.0
+    }
+}
+
+/// Per-neuron parameters of a leaky integrate-and-fire neuron, the neuron
+/// model of [`ModelBuilder::new`].
+pub type NeuronParams = LifParams;
+
+/// Builds a [`Model`] with an arbitrary topology.
+///
+/// Synapses can be added in any order, `build` sorts them into the CSR layout.
+/// Synapses of the same presynaptic neuron keep the order they were added in.
+#[derive(Default)]
+pub struct ModelBuilder<N: NeuronModel = Lif> {
+    neurons: N,
+    /// (pre, post, weight, delay)
+    synapses: Vec<(u32, u32, f32, u16)>,
+    /// Neuron positions, present once any neuron was placed
+    coordinates: Option<Vec<[f32; 3]>>,
+    /// Kinds of the first neurons, the rest are excitatory
+    kinds: Vec<NeuronKind>,
+    dale: bool,
+    populations: Vec<Population>,
+}
+
+impl ModelBuilder {
+    /// Builder for a model of leaky integrate-and-fire neurons.
+    pub fn new() -> Self {
+        Self::with_neurons(Lif::new())
+    }
+
+    pub fn with_capacity(neurons: usize, synapses: usize) -> Self {
+        let mut builder = Self::with_neurons(Lif::with_capacity(neurons));
+        builder.synapses.reserve(synapses);
+        builder
+    }
+}
+
+impl<N: NeuronModel> ModelBuilder<N> {
+    /// Builder for a model driven by `neurons`.
+    /// Neurons already in the population keep their ids.
+    pub fn with_neurons(neurons: N) -> Self {
+        Self {
+            neurons,
+            synapses: Vec::new(),
+            coordinates: None,
+            kinds: Vec::new(),
+            dale: false,
+            populations: Vec::new(),
+        }
+    }
+
+    pub fn add_neuron(&mut self, params: N::Params) -> NeuronId {
+        let id = NeuronId(self.neurons.len() as u32);
+        self.neurons.push(params);
+        id
+    }
+
+    /// Adds `count` neurons with the same parameters and returns the id of the first one.
+    /// The ids of the added neurons are contiguous.
+    pub fn add_neurons(&mut self, count: usize, params: N::Params) -> NeuronId {
+        let first = NeuronId(self.neurons.len() as u32);
+        for _ in 0..count {
+            self.neurons.push(params.clone());
+        }
+        first
+    }
+
+    /// Like [`add_neurons`](Self::add_neurons), tagging the neurons with `kind`.
+    pub fn add_neurons_of_kind(
+        &mut self,
+        count: usize,
+        params: N::Params,
+        kind: NeuronKind,
+    ) -> NeuronId {
+        let first = self.add_neurons(count, params);
+        for i in 0..count {
+            self.set_kind(NeuronId(first.0 + i as u32), kind);
+        }
+        first
+    }
+
+    pub fn set_kind(&mut self, neuron: NeuronId, kind: NeuronKind) {
+        if self.kinds.len() <= neuron.index() {
+            self.kinds
+                .resize(neuron.index() + 1, NeuronKind::Excitatory);
+        }
+        self.kinds[neuron.index()] = kind;
+    }
+
+    /// Adds `count` neurons with the same parameters as a named population.
+    ///
+    /// Panics if the name is taken.
+    pub fn add_population(
+        &mut self,
+        name: impl Into<String>,
+        count: usize,
+        params: N::Params,
+    ) -> Population {
+        self.try_add_population(name, count, params)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like [`add_population`](Self::add_population), failing with
+    /// [`Error::DuplicatePopulation`] if the name is taken.
+    pub fn try_add_population(
+        &mut self,
+        name: impl Into<String>,
+        count: usize,
+        params: N::Params,
+    ) -> Result<Population, Error> {
+        let name = name.into();
+        if self.population(&name).is_some() {
+            return Err(Error::DuplicatePopulation(name));
+        }
+        let first = self.add_neurons(count, params).0;
+        let population = Population {
+            name,
+            range: first..first + count as u32,
+        };
+        self.populations.push(population.clone());
+        Ok(population)
+    }
+
+    /// Like [`add_population`](Self::add_population), tagging the neurons with `kind`.
+    pub fn add_population_of_kind(
+        &mut self,
+        name: impl Into<String>,
+        count: usize,
+        params: N::Params,
+        kind: NeuronKind,
+    ) -> Population {
+        let population = self.add_population(name, count, params);
+        for neuron in population.neurons() {
+            self.set_kind(NeuronId(neuron), kind);
+        }
+        population
+    }
+
+    pub fn population(&self, name: &str) -> Option<&Population> {
+        self.populations.iter().find(|p| p.name == name)
+    }
+
+    /// Connect `pre` to `post` following `projection`, drawing random
+    /// choices and weights from `seed`.
+    ///
+    /// Panics if a one-to-one projection joins populations of different sizes,
+    /// the in-degree is larger than the number of possible sources, or the
+    /// delay is 0.
+    pub fn project(
+        &mut self,
+        pre: &Population,
+        post: &Population,
+        projection: &Projection,
+        seed: u64,
+    ) -> &mut Self {
+        self.try_project(pre, post, projection, seed)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like [`project`](Self::project), failing with
+    /// [`Error::InvalidProjection`] or [`Error::ZeroDelay`] before any synapse
+    /// is added.
+    pub fn try_project(
+        &mut self,
+        pre: &Population,
+        post: &Population,
+        projection: &Projection,
+        seed: u64,
+    ) -> Result<&mut Self, Error> {
+        check_projection(pre, post, projection)?;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let allowed = |a: u32, b: u32| projection.autapses || a != b;
+        let connect = |builder: &mut Self, rng: &mut StdRng, a: u32, b: u32| {
+            let w = projection.weights.sample(rng);
+            builder.connect_delayed(NeuronId(a), NeuronId(b), w, projection.delay);
+        };
+
+        match projection.connectivity {
+            Connectivity::OneToOne => {
+                for (a, b) in pre.neurons().zip(post.neurons()) {
+                    if allowed(a, b) {
+                        connect(self, &mut rng, a, b);
+                    }
+                }
+            }
+            Connectivity::AllToAll => {
+                for a in pre.neurons() {
+                    for b in post.neurons().filter(|&b| allowed(a, b)) {
+                        connect(self, &mut rng, a, b);
+                    }
+                }
+            }
+            Connectivity::FixedProbability(p) => {
+                let p = p.clamp(0.0, 1.0);
+                for a in pre.neurons() {
+                    for b in post.neurons() {
+                        if allowed(a, b) && rng.random_bool(p) {
+                            connect(self, &mut rng, a, b);
+                        }
+                    }
+                }
+            }
+            Connectivity::FixedInDegree(k) => {
+                let mut sources = Vec::with_capacity(pre.len());
+                for b in post.neurons() {
+                    sources.clear();
+                    sources.extend(pre.neurons().filter(|&a| allowed(a, b)));
+                    // Partial Fisher-Yates shuffle picks k distinct sources
+                    for i in 0..k {
+                        let j = rng.random_range(i..sources.len());
+                        sources.swap(i, j);
+                        connect(self, &mut rng, sources[i], b);
+                    }
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    /// Build a model that enforces Dale's law, see [`Model::enforce_dale`].
+    pub fn enforce_dale(&mut self) -> &mut Self {
+        self.dale = true;
+        self
+    }
+
+    /// Adds a synapse from `pre` to `post` that delivers on the next tick.
+    /// Indices are only checked in `build`, so neurons may be added afterwards.
+    pub fn connect(&mut self, pre: NeuronId, post: NeuronId, weight: f32) -> &mut Self {
+        self.connect_delayed(pre, post, weight, DEFAULT_SYNAPSE_DELAY)
+    }
+
+    /// Adds a synapse from `pre` to `post` that delivers `delay` ticks after `pre` spiked.
+    ///
+    /// Panics if `delay` is 0.
+    pub fn connect_delayed(
+        &mut self,
+        pre: NeuronId,
+        post: NeuronId,
+        weight: f32,
+        delay: u16,
+    ) -> &mut Self {
+        self.try_connect_delayed(pre, post, weight, delay)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like [`connect_delayed`](Self::connect_delayed), failing with
+    /// [`Error::ZeroDelay`] if `delay` is 0.
+    pub fn try_connect_delayed(
+        &mut self,
+        pre: NeuronId,
+        post: NeuronId,
+        weight: f32,
+        delay: u16,
+    ) -> Result<&mut Self, Error> {
+        if delay == 0 {
+            return Err(Error::ZeroDelay);
+        }
+        self.synapses.push((pre.0, post.0, weight, delay));
+        Ok(self)
+    }
+
+    /// Adds a neuron at `position`.
+    pub fn add_neuron_at(&mut self, params: N::Params, position: [f32; 3]) -> NeuronId {
+        let id = self.add_neuron(params);
+        self.set_position(id, position);
+        id
+    }
+
+    /// Places a neuron. Once any neuron has a position, all others without
+    /// one sit at the origin.
+    pub fn set_position(&mut self, neuron: NeuronId, position: [f32; 3]) {
+        let n = self.neurons.len();
+        let coordinates = self.coordinates.get_or_insert_with(Vec::new);
+        coordinates.resize(n, [0.0; 3]);
+        coordinates[neuron.index()] = position;
+    }
+
+    /// Position of a neuron, `None` until it or a later neuron was placed.
+    pub fn position(&self, neuron: NeuronId) -> Option<[f32; 3]> {
+        self.coordinates.as_ref()?.get(neuron.index()).copied()
+    }
+
+    /// Connect every ordered pair of distinct neurons with the probability of
+    /// `profile` at their distance, weight and delay also by distance.
+    /// Without `delays` the synapses deliver on the next tick.
+    ///
+    /// Panics if no neuron has been placed.
+    pub fn connect_by_distance(
+        &mut self,
+        profile: DistanceProfile,
+        weights: DistanceWeight,
+        delays: Option<DistanceDelay>,
+        seed: u64,
+    ) -> &mut Self {
+        let n = self.neurons.len();
+        let mut coordinates = self
+            .coordinates
+            .clone()
+            .expect("connecting by distance needs neuron positions");
+        coordinates.resize(n, [0.0; 3]);
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        for pre in 0..n {
+            for post in 0..n {
+                if pre == post {
+                    continue;
+                }
+                let d = distance(coordinates[pre], coordinates[post]);
+                if rng.random_bool(profile.probability(d)) {
+                    let delay = delays.map_or(1, |delays| delays.delay(d));
+                    self.connect_delayed(
+                        NeuronId(pre as u32),
+                        NeuronId(post as u32),
+                        weights.weight(d),
+                        delay,
+                    );
+                }
+            }
+        }
+        self
+    }
+
+    pub fn neuron_count(&self) -> usize {
+        self.neurons.len()
+    }
+
+    pub fn synapse_count(&self) -> usize {
+        self.synapses.len()
+    }
+
+    /// Lay out the synapses as CSR and build the model.
+    ///
+    /// Panics if a synapse references a neuron that was never added.
+    pub fn build(self) -> Model<N> {
+        self.try_build().unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like [`build`](Self::build), failing with [`Error::InvalidNeuron`] if a
+    /// synapse references a neuron that was never added.
+    pub fn try_build(self) -> Result<Model<N>, Error> {
+        let n = self.neurons.len();
+
+        for &(pre, post, _, _) in &self.synapses {
+            if let Some(index) = [pre, post].into_iter().find(|&i| i as usize >= n) {
+                return Err(Error::InvalidNeuron { index, count: n });
+            }
+        }
+
+        // Counting sort by presynaptic neuron, stable w.r.t. insertion order.
+        let mut out_offset = vec![0u32; n + 1];
+        for &(pre, _, _, _) in &self.synapses {
+            out_offset[pre as usize + 1] += 1;
+        }
+        for i in 0..n {
+            out_offset[i + 1] += out_offset[i];
+        }
+
+        let mut cursor = out_offset.clone();
+        let mut receiver = vec![0u32; self.synapses.len()];
+        let mut weight = vec![0.0f32; self.synapses.len()];
+        let mut delay = vec![0u16; self.synapses.len()];
+        for &(pre, post, w, d) in &self.synapses {
+            let slot = cursor[pre as usize] as usize;
+            cursor[pre as usize] += 1;
+            receiver[slot] = post;
+            weight[slot] = w;
+            delay[slot] = d;
+        }
+
+        let mut coordinates = self.coordinates;
+        if let Some(coordinates) = &mut coordinates {
+            coordinates.resize(n, [0.0; 3]);
+        }
+        let mut kinds = self.kinds;
+        kinds.resize(n, NeuronKind::Excitatory);
+        let mut model = Model::from_csr(self.neurons, out_offset, receiver, weight, delay);
+        model.coordinates = coordinates;
+        model.kinds = kinds;
+        model.populations = self.populations;
+        if self.dale {
+            model.enforce_dale();
+        }
+        Ok(model)
+    }
+}
+
+/// Whether `projection` can connect `pre` to `post`.
+fn check_projection(
+    pre: &Population,
+    post: &Population,
+    projection: &Projection,
+) -> Result<(), Error> {
+    if projection.delay == 0 {
+        return Err(Error::ZeroDelay);
+    }
+    let invalid = |problem| Error::InvalidProjection {
+        pre: pre.name.clone(),
+        post: post.name.clone(),
+        problem,
+    };
+    match projection.connectivity {
+        Connectivity::OneToOne if pre.len() != post.len() => {
+            Err(invalid("one-to-one needs populations of equal size"))
+        }
+        Connectivity::FixedInDegree(k) => {
+            // Without autapses a neuron in both populations has one source less
+            let overlap = post.neurons().any(|b| pre.contains(b));
+            let sources = pre.len() - usize::from(overlap && !projection.autapses);
+            if k > sources {
+                Err(invalid("in-degree exceeds the possible sources"))
+            } else {
+                Ok(())
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Connectivity, Error, Model, ModelBuilder, NeuronId, NeuronParams, Projection, WeightDist,
+    };
+
+    /// Synapses added out of order land grouped by presynaptic neuron, each
+    /// group in the order it was connected.
+    #[test]
+    fn build_sorts_by_sender() {
+        let mut builder = ModelBuilder::new();
+        builder.add_neurons(4, NeuronParams::default());
+        builder.connect_delayed(NeuronId(2), NeuronId(0), 0.1, 3);
+        builder.connect(NeuronId(0), NeuronId(3), 0.2);
+        builder.connect_delayed(NeuronId(2), NeuronId(1), 0.3, 2);
+        builder.connect(NeuronId(0), NeuronId(1), 0.4);
+        builder.connect(NeuronId(3), NeuronId(3), 0.5);
+        builder.connect(NeuronId(2), NeuronId(3), 0.6);
+        let model: Model = builder.try_build().unwrap();
+        let topology = model.topology();
+        assert_eq!(topology.out_offset, [0, 2, 2, 5, 6]);
+        assert_eq!(topology.receiver, [3, 1, 0, 1, 3, 3]);
+        assert_eq!(topology.weight, [0.2, 0.4, 0.1, 0.3, 0.6, 0.5]);
+        assert_eq!(topology.delay, [1, 1, 3, 2, 1, 1]);
+    }
+
+    /// A synapse to or from a neuron that was never added is an error.
+    #[test]
+    fn build_rejects_unknown_neurons() {
+        let mut builder = ModelBuilder::new();
+        builder.add_neurons(3, NeuronParams::default());
+        builder.connect(NeuronId(0), NeuronId(1), 0.5);
+        builder.connect(NeuronId(1), NeuronId(3), 0.5);
+        assert!(matches!(
+            builder.try_build(),
+            Err::<Model, _>(Error::InvalidNeuron { index: 3, count: 3 })
+        ));
+    }
+
+    /// The `try_` forms report bad input and leave the builder as it was.
+    #[test]
+    fn try_forms_reject_bad_input() {
+        let mut builder = ModelBuilder::new();
+        let params = NeuronParams::default();
+        let a = builder.try_add_population("a", 3, params).unwrap();
+        let b = builder.try_add_population("b", 4, params).unwrap();
+        assert!(matches!(
+            builder.try_add_population("a", 2, params),
+            Err(Error::DuplicatePopulation(name)) if name == "a"
+        ));
+        assert!(matches!(
+            builder.try_connect_delayed(NeuronId(0), NeuronId(1), 0.5, 0),
+            Err(Error::ZeroDelay)
+        ));
+
+        let weights = WeightDist::Constant(0.5);
+        let one_to_one = Projection::new(Connectivity::OneToOne, weights);
+        assert!(matches!(
+            builder.try_project(&a, &b, &one_to_one, 1),
+            Err(Error::InvalidProjection { .. })
+        ));
+        let in_degree = Projection::new(Connectivity::FixedInDegree(3), weights);
+        assert!(matches!(
+            builder.try_project(&a, &a, &in_degree, 1),
+            Err(Error::InvalidProjection { .. })
+        ));
+        assert!(matches!(
+            one_to_one.try_with_delay(0),
+            Err(Error::ZeroDelay)
+        ));
+        assert_eq!(builder.synapse_count(), 0);
+
+        builder.try_project(&b, &a, &in_degree, 1).unwrap();
+        assert_eq!(builder.synapse_count(), 9);
+        assert_eq!(builder.neuron_count(), 7);
+    }
+}
@@ -0,0 +1,88 @@
+/// Reverse (CSC) index over the CSR synapse arrays of a [`Model`](crate::Model).
+///
+/// Incoming synapses of neuron i are `synapse[in_offset[i]..in_offset[i+1]]`,
+/// ordered by synapse index. `sender` holds the presynaptic neuron of each entry.
+#[derive(Clone, Debug, Default)]
+pub struct ReverseIndex {
+    in_offset: Vec<u32>,
+    synapse: Vec<u32>,
+    sender: Vec<u32>,
+}
+
+impl ReverseIndex {
+    pub fn new(out_offset: &[u32], receiver: &[u32]) -> Self {
+        let n = out_offset.len().saturating_sub(1);
+
+        let mut in_offset = vec![0u32; n + 1];
+        for &recv in receiver {
+            in_offset[recv as usize + 1] += 1;
+        }
+        for i in 0..n {
+            in_offset[i + 1] += in_offset[i];
+        }
+
+        let mut cursor = in_offset.clone();
+        let mut synapse = vec![0u32; receiver.len()];
+        let mut sender = vec![0u32; receiver.len()];
+        for pre in 0..n {
+            let start = out_offset[pre] as usize;
+            let end = out_offset[pre + 1] as usize;
+            for (j, &recv) in (start..end).zip(&receiver[start..end]) {
+                let recv = recv as usize;
+                let slot = cursor[recv] as usize;
+                cursor[recv] += 1;
+                synapse[slot] = j as u32;
+                sender[slot] = pre as u32;
+            }
+        }
+
+        Self {
+            in_offset,
+            synapse,
+            sender,
+        }
+    }
+
+    /// Indices of the synapses ending at `neuron`.
+    pub fn incoming(&self, neuron: usize) -> &[u32] {
+        &self.synapse[self.range(neuron)]
+    }
+
+    /// Presynaptic neurons of the synapses ending at `neuron`,
+    /// parallel to [`incoming`](Self::incoming).
+    pub fn senders(&self, neuron: usize) -> &[u32] {
+        &self.sender[self.range(neuron)]
+    }
+
+    fn range(&self, neuron: usize) -> std::ops::Range<usize> {
+        self.in_offset[neuron] as usize..self.in_offset[neuron + 1] as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReverseIndex;
+    use crate::{Model, WeightDist};
+
+    /// The index lists the same synapses as a scan of the CSR arrays.
+    #[test]
+    fn matches_a_scan() {
+        let model = Model::erdos_renyi(70, 0.1, WeightDist::Constant(0.1), 2);
+        let topology = model.topology();
+        let index = ReverseIndex::new(&topology.out_offset, &topology.receiver);
+        for i in 0..70 {
+            let (mut incoming, mut senders) = (Vec::new(), Vec::new());
+            for pre in 0..70u32 {
+                for j in topology.outgoing(pre) {
+                    if topology.receiver[j] == i {
+                        incoming.push(j as u32);
+                        senders.push(pre);
+                    }
+                }
+            }
+            assert_eq!(index.incoming(i as usize), incoming);
+            assert_eq!(index.senders(i as usize), senders);
+        }
+        assert!(model.synapse_count() > 300);
+    }
+}
@@ -0,0 +1,578 @@
+//! Reading class answers back out of a [`Model`].
+//!
+//! A decoder watches groups of output neurons, one group per class. Call
+//! [`Decoder::observe`] after every [`Model::tick`] of a presentation and
+//! [`Decoder::decide`] at its end, then [`Decoder::reset`] before the next one.
+
+use crate::{Model, NeuronModel};
+
+/// Outcome of a decoder: the winning class and the score of every class.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decision {
+    /// Class with the highest score, ties go to the lower index and NaN
+    /// scores never win. `None` if there is no evidence, e.g. no output
+    /// neuron spiked.
+    pub winner: Option<usize>,
+    pub scores: Vec<f32>,
+}
+
+impl Decision {
+    /// Pick the highest score as the winner, `None` if every score is NaN.
+    pub fn from_scores(scores: Vec<f32>) -> Self {
+        let mut winner: Option<usize> = None;
+        for (k, &s) in scores.iter().enumerate() {
+            if !s.is_nan() && winner.is_none_or(|w| s > scores[w]) {
+                winner = Some(k);
+            }
+        }
+        Self { winner, scores }
+    }
+}
+
+pub trait Decoder {
+    /// Number of classes
+    fn classes(&self) -> usize;
+
+    /// Forget everything observed so far.
+    fn reset(&mut self);
+
+    /// Look at the model after a tick.
+    fn observe<N: NeuronModel>(&mut self, model: &Model<N>);
+
+    fn decide(&self) -> Decision;
+}
+
+/// Output neurons of each class.
+#[derive(Clone, Debug)]
+struct Outputs {
+    groups: Vec<Vec<u32>>,
+}
+
+impl Outputs {
+    fn new(groups: impl IntoIterator<Item = impl IntoIterator<Item = u32>>) -> Self {
+        Self {
+            groups: groups
+                .into_iter()
+                .map(|g| g.into_iter().collect())
+                .collect(),
+        }
+    }
+
+    fn single(neurons: impl IntoIterator<Item = u32>) -> Self {
+        Self::new(neurons.into_iter().map(std::iter::once))
+    }
+}
+
+/// Scores each class by the number of spikes of its neurons.
+#[derive(Clone, Debug)]
+pub struct SpikeCountDecoder {
+    outputs: Outputs,
+    counts: Vec<u32>,
+}
+
+impl SpikeCountDecoder {
+    /// One output neuron per class.
+    pub fn new(neurons: impl IntoIterator<Item = u32>) -> Self {
+        Self::with_outputs(Outputs::single(neurons))
+    }
+
+    /// A group of output neurons per class, their spikes add up.
+    pub fn grouped(groups: impl IntoIterator<Item = impl IntoIterator<Item = u32>>) -> Self {
+        Self::with_outputs(Outputs::new(groups))
+    }
+
+    fn with_outputs(outputs: Outputs) -> Self {
+        Self {
+            counts: vec![0; outputs.groups.len()],
+            outputs,
+        }
+    }
+
+    pub fn counts(&self) -> &[u32] {
+        &self.counts
+    }
+}
+
+impl Decoder for SpikeCountDecoder {
+    fn classes(&self) -> usize {
+        self.outputs.groups.len()
+    }
+
+    fn reset(&mut self) {
+        self.counts.fill(0);
+    }
+
+    fn observe<N: NeuronModel>(&mut self, model: &Model<N>) {
+        let spiked = model.spiked();
+        for (count, group) in self.counts.iter_mut().zip(&self.outputs.groups) {
+            *count += group.iter().filter(|&&i| spiked[i as usize]).count() as u32;
+        }
+    }
+
+    fn decide(&self) -> Decision {
+        let mut decision = Decision::from_scores(self.counts.iter().map(|&c| c as f32).collect());
+        if self.counts.iter().all(|&c| c == 0) {
+            decision.winner = None;
+        }
+        decision
+    }
+}
+
+/// The class whose neurons spike first wins. Scores are `1 / (1 + latency)`,
+/// with the latency in observed ticks, and 0 for classes that never spiked.
+#[derive(Clone, Debug)]
+pub struct FirstSpikeDecoder {
+    outputs: Outputs,
+    /// Ticks observed since the last reset
+    elapsed: u32,
+    first: Vec<Option<u32>>,
+}
+
+impl FirstSpikeDecoder {
+    pub fn new(neurons: impl IntoIterator<Item = u32>) -> Self {
+        Self::with_outputs(Outputs::single(neurons))
+    }
+
+    pub fn grouped(groups: impl IntoIterator<Item = impl IntoIterator<Item = u32>>) -> Self {
+        Self::with_outputs(Outputs::new(groups))
+    }
+
+    fn with_outputs(outputs: Outputs) -> Self {
+        Self {
+            first: vec![None; outputs.groups.len()],
+            elapsed: 0,
+            outputs,
+        }
+    }
+
+    /// Latency of the first spike of each class.
+    pub fn latencies(&self) -> &[Option<u32>] {
+        &self.first
+    }
+}
+
+impl Decoder for FirstSpikeDecoder {
+    fn classes(&self) -> usize {
+        self.outputs.groups.len()
+    }
+
+    fn reset(&mut self) {
+        self.elapsed = 0;
+        self.first.fill(None);
+    }
+
+    fn observe<N: NeuronModel>(&mut self, model: &Model<N>) {
+        let spiked = model.spiked();
+        for (first, group) in self.first.iter_mut().zip(&self.outputs.groups) {
+            if first.is_none() && group.iter().any(|&i| spiked[i as usize]) {
+                *first = Some(self.elapsed);
+            }
+        }
+        self.elapsed += 1;
+    }
+
+    fn decide(&self) -> Decision {
+        let scores = self
+            .first
+            .iter()
+            .map(|t| t.map_or(0.0, |t| 1.0 / (1 + t) as f32))
+            .collect();
+        let mut decision = Decision::from_scores(scores);
+        if self.first.iter().all(Option::is_none) {
+            decision.winner = None;
+        }
+        decision
+    }
+}
+
+/// Scores each class by the highest membrane potential its neurons reached.
+/// Useful for non-spiking output layers.
+#[derive(Clone, Debug)]
+pub struct MaxPotentialDecoder {
+    outputs: Outputs,
+    max: Vec<f32>,
+}
+
+impl MaxPotentialDecoder {
+    pub fn new(neurons: impl IntoIterator<Item = u32>) -> Self {
+        Self::with_outputs(Outputs::single(neurons))
+    }
+
+    pub fn grouped(groups: impl IntoIterator<Item = impl IntoIterator<Item = u32>>) -> Self {
+        Self::with_outputs(Outputs::new(groups))
+    }
+
+    fn with_outputs(outputs: Outputs) -> Self {
+        Self {
+            max: vec![f32::NEG_INFINITY; outputs.groups.len()],
+            outputs,
+        }
+    }
+}
+
+impl Decoder for MaxPotentialDecoder {
+    fn classes(&self) -> usize {
+        self.outputs.groups.len()
+    }
+
+    fn reset(&mut self) {
+        self.max.fill(f32::NEG_INFINITY);
+    }
+
+    fn observe<N: NeuronModel>(&mut self, model: &Model<N>) {
+        let v = model.neuron_vs();
+        for (max, group) in self.max.iter_mut().zip(&self.outputs.groups) {
+            for &i in group {
+                *max = max.max(v[i as usize]);
+            }
+        }
+    }
+
+    fn decide(&self) -> Decision {
+        let mut decision = Decision::from_scores(self.max.clone());
+        if self.max.iter().all(|m| *m == f32::NEG_INFINITY) {
+            decision.winner = None;
+        }
+        decision
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FitError {
+    #[error("no training samples")]
+    Empty,
+    #[error("sample {0} has the wrong number of features or targets")]
+    LengthMismatch(usize),
+    #[error("the normal equations are singular, increase the ridge penalty")]
+    Singular,
+    #[error("sample {sample} has label {label}, but there are only {classes} classes")]
+    UnknownClass {
+        sample: usize,
+        label: usize,
+        classes: usize,
+    },
+}
+
+/// Linear map from the spike counts of feature neurons to class scores,
+/// trained by ridge regression.
+///
+/// The features of a presentation are the spike counts of the `neurons`,
+/// collected by [`observe`](Decoder::observe). Training takes such feature
+/// vectors, with [`features`](Self::features) giving the current one.
+#[derive(Clone, Debug)]
+pub struct LinearReadout {
+    neurons: Vec<u32>,
+    outputs: usize,
+    /// `outputs` rows of one weight per feature followed by the bias
+    weights: Vec<f32>,
+    counts: Vec<f32>,
+}
+
+impl LinearReadout {
+    /// An untrained readout with all weights 0.
+    pub fn new(neurons: impl IntoIterator<Item = u32>, outputs: usize) -> Self {
+        let neurons: Vec<u32> = neurons.into_iter().collect();
+        Self {
+            weights: vec![0.0; outputs * (neurons.len() + 1)],
+            counts: vec![0.0; neurons.len()],
+            neurons,
+            outputs,
+        }
+    }
+
+    pub fn neurons(&self) -> &[u32] {
+        &self.neurons
+    }
+
+    pub fn outputs(&self) -> usize {
+        self.outputs
+    }
+
+    /// Weights of output `k`, the last one is the bias.
+    pub fn weights(&self, k: usize) -> &[f32] {
+        let stride = self.neurons.len() + 1;
+        &self.weights[k * stride..(k + 1) * stride]
+    }
+
+    /// Features observed since the last reset.
+    pub fn features(&self) -> &[f32] {
+        &self.counts
+    }
+
+    /// Outputs for a feature vector.
+    pub fn predict(&self, features: &[f32]) -> Vec<f32> {
+        debug_assert_eq!(features.len(), self.neurons.len());
+        (0..self.outputs)
+            .map(|k| {
+                let (w, bias) = self.weights(k).split_at(features.len());
+                w.iter().zip(features).map(|(w, x)| w * x).sum::<f32>() + bias[0]
+            })
+            .collect()
+    }
+
+    /// Least squares fit of the outputs to `targets`, with the penalty
+    /// `ridge * |w|²` on all weights but the bias.
+    pub fn fit(
+        &mut self,
+        features: &[Vec<f32>],
+        targets: &[Vec<f32>],
+        ridge: f32,
+    ) -> Result<(), FitError> {
+        if features.is_empty() {
+            return Err(FitError::Empty);
+        }
+        let n = self.neurons.len();
+        for (s, (x, y)) in features.iter().zip(targets).enumerate() {
+            if x.len() != n || y.len() != self.outputs {
+                return Err(FitError::LengthMismatch(s));
+            }
+        }
+        if features.len() != targets.len() {
+            return Err(FitError::LengthMismatch(features.len().min(targets.len())));
+        }
+
+        // Normal equations (XᵀX + λI) W = XᵀY over features extended by a 1 for the bias
+        let d = n + 1;
+        let mut xtx = vec![0.0f64; d * d];
+        let mut xty = vec![0.0f64; d * self.outputs];
+        let mut row = vec![0.0f64; d];
+        for (x, y) in features.iter().zip(targets) {
+            for (r, &v) in row.iter_mut().zip(x) {
+                *r = v as f64;
+            }
+            row[n] = 1.0;
+            for a in 0..d {
+                for b in a..d {
+                    xtx[a * d + b] += row[a] * row[b];
+                }
+                for (k, &t) in y.iter().enumerate() {
+                    xty[k * d + a] += row[a] * t as f64;
+                }
+            }
+        }
+        for a in 0..d {
+            for b in 0..a {
+                xtx[a * d + b] = xtx[b * d + a];
+            }
+        }
+        for a in 0..n {
+            xtx[a * d + a] += ridge as f64;
+        }
+
+        let l = cholesky(&xtx, d).ok_or(FitError::Singular)?;
+        for (k, rhs) in xty.chunks_exact(d).enumerate() {
+            let w = cholesky_solve(&l, d, rhs);
+            for (dst, src) in self.weights[k * d..(k + 1) * d].iter_mut().zip(w) {
+                *dst = src as f32;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fit one output per class to one-hot targets of the `labels`.
+    pub fn fit_classes(
+        &mut self,
+        features: &[Vec<f32>],
+        labels: &[usize],
+        ridge: f32,
+    ) -> Result<(), FitError> {
+        let classes = self.outputs;
+        let targets = labels
+            .iter()
+            .enumerate()
+            .map(|(sample, &label)| {
+                if label >= classes {
+                    return Err(FitError::UnknownClass {
+                        sample,
+                        label,
+                        classes,
+                    });
+                }
+                let mut t = vec![0.0; classes];
+                t[label] = 1.0;
+                Ok(t)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.fit(features, &targets, ridge)
+    }
+}
+
+impl Decoder for LinearReadout {
+    fn classes(&self) -> usize {
+        self.outputs
+    }
+
+    fn reset(&mut self) {
+        self.counts.fill(0.0);
+    }
+
+    fn observe<N: NeuronModel>(&mut self, model: &Model<N>) {
+        let spiked = model.spiked();
+        for (count, &i) in self.counts.iter_mut().zip(&self.neurons) {
+            if spiked[i as usize] {
+                *count += 1.0;
+            }
+        }
+    }
+
+    fn decide(&self) -> Decision {
+        Decision::from_scores(self.predict(&self.counts))
+    }
+}
+
+/// Lower triangular `L` with `L Lᵀ = a` for a symmetric positive definite
+/// `d × d` matrix, `None` if it isn't.
+fn cholesky(a: &[f64], d: usize) -> Option<Vec<f64>> {
+    let mut l = vec![0.0; d * d];
+    for i in 0..d {
+        for j in 0..=i {
+            let dot: f64 = (0..j).map(|k| l[i * d + k] * l[j * d + k]).sum();
+            if i == j {
+                let pivot = a[i * d + i] - dot;
+                if pivot <= 0.0 || !pivot.is_finite() {
+                    return None;
+                }
+                l[i * d + i] = pivot.sqrt();
+            } else {
+                l[i * d + j] = (a[i * d + j] - dot) / l[j * d + j];
+            }
+        }
+    }
+    Some(l)
+}
+
+/// Solve `L Lᵀ x = b` for a factor from [`cholesky`].
+fn cholesky_solve(l: &[f64], d: usize, b: &[f64]) -> Vec<f64> {
+    let mut y = vec![0.0; d];
+    for i in 0..d {
+        let dot: f64 = (0..i).map(|k| l[i * d + k] * y[k]).sum();
+        y[i] = (b[i] - dot) / l[i * d + i];
+    }
+    let mut x = vec![0.0; d];
+    for i in (0..d).rev() {
+        let dot: f64 = (i + 1..d).map(|k| l[k * d + i] * x[k]).sum();
+        x[i] = (y[i] - dot) / l[i * d + i];
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use super::{
+        Decision, Decoder, FirstSpikeDecoder, FitError, LinearReadout, MaxPotentialDecoder,
+        SpikeCountDecoder,
+    };
+    use crate::{Model, ModelBuilder, NeuronParams};
+
+    #[test]
+    fn from_scores_breaks_ties_low_and_skips_nan() {
+        let nan = f32::NAN;
+        let winner = |scores: &[f32]| Decision::from_scores(scores.to_vec()).winner;
+        assert_eq!(winner(&[nan, 1.0, 3.0, 3.0, nan]), Some(2));
+        assert_eq!(winner(&[nan, f32::NEG_INFINITY]), Some(1));
+        assert_eq!(winner(&[2.0, nan, 5.0]), Some(2));
+        assert_eq!(winner(&[nan, nan]), None);
+        assert_eq!(winner(&[]), None);
+    }
+
+    /// Without noise or penalty the fit finds the weights that made the targets.
+    #[test]
+    fn fit_recovers_known_weights() {
+        let truth = [[0.5, -1.0, 2.0, 0.25], [-0.75, 0.0, 1.5, -1.0]];
+        let mut rng = StdRng::seed_from_u64(5);
+        let features: Vec<Vec<f32>> = (0..50)
+            .map(|_| (0..3).map(|_| rng.random_range(0.0..4.0)).collect())
+            .collect();
+        let targets: Vec<Vec<f32>> = features
+            .iter()
+            .map(|x| {
+                truth
+                    .iter()
+                    .map(|w| w[0] * x[0] + w[1] * x[1] + w[2] * x[2] + w[3])
+                    .collect()
+            })
+            .collect();
+
+        let mut readout = LinearReadout::new(0..3, 2);
+        readout.fit(&features, &targets, 0.0).unwrap();
+        for (k, w) in truth.iter().enumerate() {
+            for (fitted, expected) in readout.weights(k).iter().zip(w) {
+                assert!((fitted - expected).abs() < 1e-3, "{fitted} != {expected}");
+            }
+        }
+        let prediction = readout.predict(&features[0]);
+        assert!((prediction[1] - targets[0][1]).abs() < 1e-3);
+    }
+
+    #[test]
+    fn fit_rejects_bad_training_sets() {
+        let mut readout = LinearReadout::new(0..2, 2);
+        let features = vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![1.0, 1.0]];
+        assert!(matches!(
+            readout.fit_classes(&features, &[0, 2, 1], 0.1),
+            Err(FitError::UnknownClass {
+                sample: 1,
+                label: 2,
+                classes: 2
+            })
+        ));
+        assert!(matches!(
+            readout.fit_classes(&[], &[], 0.1),
+            Err(FitError::Empty)
+        ));
+        assert!(matches!(
+            readout.fit(&features, &[vec![1.0, 0.0]], 0.1),
+            Err(FitError::LengthMismatch(1))
+        ));
+        // Two identical samples cannot pin down two weights and a bias
+        let same = vec![vec![1.0, 1.0]; 2];
+        assert!(matches!(
+            readout.fit_classes(&same, &[0, 1], 0.0),
+            Err(FitError::Singular)
+        ));
+        readout.fit_classes(&features, &[0, 1, 1], 0.1).unwrap();
+    }
+
+    /// Three unconnected neurons, each driven on the given ticks by `current`.
+    fn drive(schedule: &[(u32, u32)], current: f32, decoder: &mut impl Decoder) {
+        let mut builder = ModelBuilder::new();
+        builder.add_neurons(3, NeuronParams::default());
+        let mut model: Model = builder.build();
+        for t in 0..8 {
+            for &(_, neuron) in schedule.iter().filter(|(tick, _)| *tick == t) {
+                model.add_input(neuron, current);
+            }
+            model.tick();
+            decoder.observe(&model);
+        }
+    }
+
+    #[test]
+    fn decoders_pick_the_expected_class() {
+        // Neuron 2 fires first, neuron 0 fires twice, neuron 1 stays silent
+        let spikes = [(0, 2), (1, 0), (5, 0)];
+
+        let mut counts = SpikeCountDecoder::new(0..3);
+        drive(&spikes, 2.0, &mut counts);
+        assert_eq!(counts.counts(), [2, 0, 1]);
+        assert_eq!(counts.decide().winner, Some(0));
+        counts.reset();
+        assert_eq!(counts.decide().winner, None);
+
+        let mut first = FirstSpikeDecoder::new(0..3);
+        drive(&spikes, 2.0, &mut first);
+        assert_eq!(first.latencies(), [Some(1), None, Some(0)]);
+        assert_eq!(first.decide().winner, Some(2));
+
+        let mut grouped = SpikeCountDecoder::grouped([vec![1, 2], vec![0]]);
+        drive(&[(0, 1), (0, 2), (3, 0)], 2.0, &mut grouped);
+        assert_eq!(grouped.decide().winner, Some(0));
+
+        // Below threshold the highest potential wins
+        let mut potential = MaxPotentialDecoder::new(0..3);
+        drive(&[(2, 1), (2, 2), (4, 2)], 0.4, &mut potential);
+        assert_eq!(potential.decide().winner, Some(2));
+    }
+}
@@ -0,0 +1,296 @@
+//! Turning real-valued data into input for a [`Model`].
+//!
+//! An encoder drives one target neuron per input value. Spike encoders inject a
+//! current pulse of `amplitude` on the ticks the value should spike on, the
+//! default of 1.0 fires a resting default LIF neuron. Present a vector with
+//! [`Model::present`], which asks the encoder for each tick's input and ticks.
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::{Model, NeuronModel};
+
+/// Current that makes a resting default LIF neuron fire
+const DEFAULT_AMPLITUDE: f32 = 1.0;
+
+pub trait Encoder {
+    /// Number of input values the encoder expects
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add the input for `values` on tick `t` of the presentation to `model`.
+    /// `t` counts from 0 on the first tick of each presentation.
+    fn apply<N: NeuronModel>(&mut self, model: &mut Model<N>, values: &[f32], t: u32);
+}
+
+impl<N: NeuronModel> Model<N> {
+    /// Present `values` through `encoder` for `ticks` ticks.
+    ///
+    /// Panics if the number of values doesn't match the encoder.
+    pub fn present<E: Encoder>(&mut self, encoder: &mut E, values: &[f32], ticks: u32) {
+        assert_eq!(
+            values.len(),
+            encoder.len(),
+            "encoder expects {} values, got {}",
+            encoder.len(),
+            values.len()
+        );
+        for t in 0..ticks {
+            encoder.apply(self, values, t);
+            self.tick();
+        }
+    }
+}
+
+/// Injects `value * gain` as a current on every tick.
+#[derive(Clone, Debug)]
+pub struct CurrentEncoder {
+    pub targets: Vec<u32>,
+    pub gain: f32,
+}
+
+impl CurrentEncoder {
+    pub fn new(targets: impl IntoIterator<Item = u32>, gain: f32) -> Self {
+        Self {
+            targets: targets.into_iter().collect(),
+            gain,
+        }
+    }
+}
+
+impl Encoder for CurrentEncoder {
+    fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    fn apply<N: NeuronModel>(&mut self, model: &mut Model<N>, values: &[f32], _t: u32) {
+        for (&target, &value) in self.targets.iter().zip(values) {
+            model.add_input(target, value * self.gain);
+        }
+    }
+}
+
+/// Poisson rate coding: a value in [0, 1] spikes with probability
+/// `value * max_rate` on every tick.
+#[derive(Clone, Debug)]
+pub struct PoissonEncoder {
+    pub targets: Vec<u32>,
+    /// Spike probability per tick at value 1
+    pub max_rate: f32,
+    pub amplitude: f32,
+    rng: StdRng,
+}
+
+impl PoissonEncoder {
+    pub fn new(targets: impl IntoIterator<Item = u32>, max_rate: f32, seed: u64) -> Self {
+        Self {
+            targets: targets.into_iter().collect(),
+            max_rate,
+            amplitude: DEFAULT_AMPLITUDE,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn with_amplitude(mut self, amplitude: f32) -> Self {
+        self.amplitude = amplitude;
+        self
+    }
+}
+
+impl Encoder for PoissonEncoder {
+    fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    fn apply<N: NeuronModel>(&mut self, model: &mut Model<N>, values: &[f32], _t: u32) {
+        for (&target, &value) in self.targets.iter().zip(values) {
+            let p = (value.clamp(0.0, 1.0) * self.max_rate).clamp(0.0, 1.0);
+            // Draw for every target so the stream doesn't depend on the values
+            if self.rng.random::<f32>() < p {
+                model.add_input(target, self.amplitude);
+            }
+        }
+    }
+}
+
+/// Deterministic rate coding: spikes are spread evenly, `value * max_rate`
+/// per tick on average.
+#[derive(Clone, Debug)]
+pub struct RateEncoder {
+    pub targets: Vec<u32>,
+    /// Spikes per tick at value 1
+    pub max_rate: f32,
+    pub amplitude: f32,
+    /// Accumulated phase of each target, spikes when it reaches 1
+    phase: Vec<f32>,
+}
+
+impl RateEncoder {
+    pub fn new(targets: impl IntoIterator<Item = u32>, max_rate: f32) -> Self {
+        let targets: Vec<u32> = targets.into_iter().collect();
+        Self {
+            phase: vec![0.0; targets.len()],
+            targets,
+            max_rate,
+            amplitude: DEFAULT_AMPLITUDE,
+        }
+    }
+
+    pub fn with_amplitude(mut self, amplitude: f32) -> Self {
+        self.amplitude = amplitude;
+        self
+    }
+}
+
+impl Encoder for RateEncoder {
+    fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    fn apply<N: NeuronModel>(&mut self, model: &mut Model<N>, values: &[f32], t: u32) {
+        if t == 0 {
+            self.phase.fill(0.0);
+        }
+        for ((&target, &value), phase) in self.targets.iter().zip(values).zip(&mut self.phase) {
+            *phase += (value.clamp(0.0, 1.0) * self.max_rate).clamp(0.0, 1.0);
+            if *phase >= 1.0 {
+                *phase -= 1.0;
+                model.add_input(target, self.amplitude);
+            }
+        }
+    }
+}
+
+/// Time-to-first-spike coding: each value in [0, 1] spikes once, 1 on the
+/// first tick and values near 0 at the end of the `window`.
+#[derive(Clone, Debug)]
+pub struct LatencyEncoder {
+    pub targets: Vec<u32>,
+    /// Ticks over which the spike times are spread
+    pub window: u32,
+    /// Values at or below this don't spike at all
+    pub threshold: f32,
+    pub amplitude: f32,
+}
+
+impl LatencyEncoder {
+    pub fn new(targets: impl IntoIterator<Item = u32>, window: u32) -> Self {
+        Self {
+            targets: targets.into_iter().collect(),
+            window,
+            threshold: 0.0,
+            amplitude: DEFAULT_AMPLITUDE,
+        }
+    }
+
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn with_amplitude(mut self, amplitude: f32) -> Self {
+        self.amplitude = amplitude;
+        self
+    }
+
+    /// Presentation tick `value` spikes on, `None` if it doesn't.
+    pub fn spike_time(&self, value: f32) -> Option<u32> {
+        if value <= self.threshold || self.window == 0 {
+            return None;
+        }
+        let latency = (1.0 - value.clamp(0.0, 1.0)) * (self.window - 1) as f32;
+        Some(latency.round() as u32)
+    }
+}
+
+impl Encoder for LatencyEncoder {
+    fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    fn apply<N: NeuronModel>(&mut self, model: &mut Model<N>, values: &[f32], t: u32) {
+        for (&target, &value) in self.targets.iter().zip(values) {
+            if self.spike_time(value) == Some(t) {
+                model.add_input(target, self.amplitude);
+            }
+        }
+    }
+}
+
+/// Population coding: each value is spread over `fields` neurons with Gaussian
+/// receptive fields evenly covering `[min, max]`. The activations of the
+/// fields, in [0, 1], are passed on to the `inner` encoder, which drives
+/// `len() * fields` neurons with the fields of each value next to each other.
+#[derive(Clone, Debug)]
+pub struct PopulationEncoder<E> {
+    pub fields: usize,
+    pub min: f32,
+    pub max: f32,
+    /// Standard deviation of each field, as a fraction of the spacing of the centres
+    pub width: f32,
+    pub inner: E,
+    activations: Vec<f32>,
+}
+
+impl<E: Encoder> PopulationEncoder<E> {
+    /// Panics if `inner` doesn't take a multiple of `fields` values.
+    pub fn new(inner: E, fields: usize, min: f32, max: f32) -> Self {
+        assert!(fields >= 1, "population coding needs at least one field");
+        assert!(
+            inner.len().is_multiple_of(fields),
+            "inner encoder length {} is not a multiple of {fields} fields",
+            inner.len()
+        );
+        Self {
+            fields,
+            min,
+            max,
+            width: 1.0,
+            activations: vec![0.0; inner.len()],
+            inner,
+        }
+    }
+
+    pub fn with_width(mut self, width: f32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Activation of each field for `value`.
+    pub fn activations(&self, value: f32) -> impl Iterator<Item = f32> + '_ {
+        let spacing = if self.fields > 1 {
+            (self.max - self.min) / (self.fields - 1) as f32
+        } else {
+            self.max - self.min
+        };
+        let sigma = (spacing * self.width).abs().max(f32::EPSILON);
+        (0..self.fields).map(move |k| {
+            let centre = if self.fields > 1 {
+                self.min + spacing * k as f32
+            } else {
+                (self.min + self.max) * 0.5
+            };
+            let d = (value - centre) / sigma;
+            (-0.5 * d * d).exp()
+        })
+    }
+}
+
+impl<E: Encoder> Encoder for PopulationEncoder<E> {
+    fn len(&self) -> usize {
+        self.inner.len() / self.fields
+    }
+
+    fn apply<N: NeuronModel>(&mut self, model: &mut Model<N>, values: &[f32], t: u32) {
+        let mut activations = std::mem::take(&mut self.activations);
+        for (chunk, &value) in activations.chunks_mut(self.fields).zip(values) {
+            for (a, field) in chunk.iter_mut().zip(self.activations(value)) {
+                *a = field;
+            }
+        }
+        self.inner.apply(model, &activations, t);
+        self.activations = activations;
+    }
+}
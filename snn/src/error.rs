@@ -0,0 +1,348 @@
+//! The crate-wide [`Error`] and checked variants of the index-taking
+//! accessors of [`Model`].
+//!
+//! The plain accessors panic on an index out of range, their `try_`
+//! counterparts return [`Error::InvalidNeuron`] or [`Error::InvalidSynapse`]
+//! instead and leave the model untouched. The same goes for the methods that
+//! attach stimuli, monitors, populations and coordinates.
+
+use std::ops::Range;
+
+use crate::{
+    Model, NeuronKey, NeuronKind, NeuronModel, PersistError, Population, SpikeMonitor,
+    SpikeMonitorId, StateMonitor, StateMonitorId, Stimulus, SynapseKey, Topology, ValidationReport,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("neuron {index} out of range for {count} neurons")]
+    InvalidNeuron { index: u32, count: usize },
+    #[error("synapse {index} out of range for {count} synapses")]
+    InvalidSynapse { index: u32, count: usize },
+    #[error("no neuron with key {0:?}")]
+    UnknownNeuron(NeuronKey),
+    #[error("no synapse with key {0:?}")]
+    UnknownSynapse(SynapseKey),
+    #[error("synapse delay must be at least one tick")]
+    ZeroDelay,
+    #[error("invalid stimulus: {0}")]
+    InvalidStimulus(&'static str),
+    #[error("population {0} already exists")]
+    DuplicatePopulation(String),
+    #[error("population {name} spans {start}..{end}, not a range of {count} neurons")]
+    InvalidPopulation {
+        name: String,
+        start: u32,
+        end: u32,
+        count: usize,
+    },
+    #[error("projection from {pre} to {post}: {problem}")]
+    InvalidProjection {
+        pre: String,
+        post: String,
+        problem: &'static str,
+    },
+    #[error("{len} positions for {count} neurons")]
+    CoordinateCount { len: usize, count: usize },
+    #[error("malformed CSR: {0}")]
+    MalformedCsr(&'static str),
+    #[error("Graphviz is not installed, `neato` was not found")]
+    GraphvizMissing,
+    #[error("Graphviz failed: {0}")]
+    Graphviz(std::io::Error),
+    #[error("failed to start simulation threads: {0}")]
+    Threads(#[from] rayon::ThreadPoolBuildError),
+    #[error("parse error: {0}")]
+    Parse(String),
+    #[error("invalid model: {0}")]
+    Invalid(ValidationReport),
+    #[error(transparent)]
+    Persist(#[from] PersistError),
+}
+
+impl Topology {
+    /// First broken CSR invariant for `neurons` neurons, `None` if the
+    /// synapses are well-formed.
+    pub(crate) fn csr_violation(&self, neurons: usize) -> Option<&'static str> {
+        let e = self.receiver.len();
+        if self.out_offset.len() != neurons + 1 {
+            return Some("out_offset needs one entry per neuron plus one");
+        }
+        if self.weight.len() != e || self.delay.len() != e {
+            return Some("synapse arrays differ in length");
+        }
+        if self.out_offset[0] != 0 || self.out_offset.windows(2).any(|w| w[0] > w[1]) {
+            return Some("out_offset is not monotone from 0");
+        }
+        if self.out_offset[neurons] as usize != e {
+            return Some("out_offset does not cover all synapses");
+        }
+        if self.receiver.iter().any(|&r| r as usize >= neurons) {
+            return Some("receiver out of range");
+        }
+        if self.delay.contains(&0) {
+            return Some("synapse delay of 0 ticks");
+        }
+        None
+    }
+}
+
+impl<N: NeuronModel> Model<N> {
+    /// Assemble a model from neurons and synapses already laid out as CSR,
+    /// checking the layout first.
+    pub fn try_from_csr(
+        neurons: N,
+        out_offset: Vec<u32>,
+        receiver: Vec<u32>,
+        weight: Vec<f32>,
+        delay: Vec<u16>,
+    ) -> Result<Self, Error> {
+        let topology = Topology {
+            out_offset,
+            receiver,
+            weight,
+            delay,
+        };
+        if let Some(violation) = topology.csr_violation(neurons.len()) {
+            return Err(Error::MalformedCsr(violation));
+        }
+        Ok(Self::from_csr(
+            neurons,
+            topology.out_offset,
+            topology.receiver,
+            topology.weight,
+            topology.delay,
+        ))
+    }
+
+    pub(crate) fn check_neuron(&self, neuron: u32) -> Result<usize, Error> {
+        let count = self.neuron_count();
+        if (neuron as usize) < count {
+            Ok(neuron as usize)
+        } else {
+            Err(Error::InvalidNeuron {
+                index: neuron,
+                count,
+            })
+        }
+    }
+
+    /// [`check_neuron`](Self::check_neuron) for each of `neurons`.
+    pub(crate) fn check_neurons(&self, neurons: &[u32]) -> Result<(), Error> {
+        neurons
+            .iter()
+            .try_for_each(|&i| self.check_neuron(i).map(|_| ()))
+    }
+
+    /// Whether every target of `stimulus` exists and its waveform is well-formed.
+    pub(crate) fn check_stimulus(&self, stimulus: &Stimulus) -> Result<(), Error> {
+        self.check_neurons(&stimulus.targets)?;
+        stimulus.waveform.check()
+    }
+
+    /// Whether `name` is free and `range` lies within the neurons.
+    pub(crate) fn check_population(&self, name: &str, range: &Range<u32>) -> Result<(), Error> {
+        if self.population(name).is_some() {
+            return Err(Error::DuplicatePopulation(name.to_owned()));
+        }
+        let count = self.neuron_count();
+        if range.start > range.end || range.end as usize > count {
+            return Err(Error::InvalidPopulation {
+                name: name.to_owned(),
+                start: range.start,
+                end: range.end,
+                count,
+            });
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_coordinates(&self, coordinates: &[[f32; 3]]) -> Result<(), Error> {
+        let count = self.neuron_count();
+        if coordinates.len() == count {
+            Ok(())
+        } else {
+            Err(Error::CoordinateCount {
+                len: coordinates.len(),
+                count,
+            })
+        }
+    }
+
+    pub(crate) fn check_synapse(&self, synapse: u32) -> Result<usize, Error> {
+        let count = self.synapse_count();
+        if (synapse as usize) < count {
+            Ok(synapse as usize)
+        } else {
+            Err(Error::InvalidSynapse {
+                index: synapse,
+                count,
+            })
+        }
+    }
+
+    pub fn try_get_charge(&self, neuron: u32) -> Result<f32, Error> {
+        self.check_neuron(neuron)?;
+        Ok(self.get_charge(neuron))
+    }
+
+    pub fn try_set_charge(&mut self, neuron: u32, charge: f32) -> Result<(), Error> {
+        self.check_neuron(neuron)?;
+        self.set_charge(neuron, charge);
+        Ok(())
+    }
+
+    pub fn try_add_input(&mut self, neuron: u32, current: f32) -> Result<(), Error> {
+        self.check_neuron(neuron)?;
+        self.add_input(neuron, current);
+        Ok(())
+    }
+
+    pub fn try_bias(&self, neuron: u32) -> Result<f32, Error> {
+        self.check_neuron(neuron)?;
+        Ok(self.bias(neuron))
+    }
+
+    pub fn try_set_bias(&mut self, neuron: u32, current: f32) -> Result<(), Error> {
+        self.check_neuron(neuron)?;
+        self.set_bias(neuron, current);
+        Ok(())
+    }
+
+    pub fn try_kind(&self, neuron: u32) -> Result<NeuronKind, Error> {
+        self.check_neuron(neuron)?;
+        Ok(self.kind(neuron))
+    }
+
+    pub fn try_set_kind(&mut self, neuron: u32, kind: NeuronKind) -> Result<(), Error> {
+        self.check_neuron(neuron)?;
+        self.set_kind(neuron, kind);
+        Ok(())
+    }
+
+    pub fn try_outgoing(&self, neuron: u32) -> Result<std::ops::Range<usize>, Error> {
+        self.check_neuron(neuron)?;
+        Ok(self.outgoing(neuron))
+    }
+
+    pub fn try_set_synapse_weight(&mut self, synapse: u32, weight: f32) -> Result<(), Error> {
+        self.check_synapse(synapse)?;
+        self.set_synapse_weight(synapse, weight);
+        Ok(())
+    }
+
+    pub fn try_neuron_key(&self, neuron: u32) -> Result<NeuronKey, Error> {
+        self.check_neuron(neuron)?;
+        Ok(self.neuron_key(neuron))
+    }
+
+    pub fn try_synapse_key(&self, synapse: u32) -> Result<SynapseKey, Error> {
+        self.check_synapse(synapse)?;
+        Ok(self.synapse_key(synapse))
+    }
+
+    pub fn try_distance(&self, a: u32, b: u32) -> Result<Option<f32>, Error> {
+        self.check_neuron(a)?;
+        self.check_neuron(b)?;
+        Ok(self.distance(a, b))
+    }
+
+    pub fn try_set_coordinates(&mut self, coordinates: Vec<[f32; 3]>) -> Result<(), Error> {
+        self.check_coordinates(&coordinates)?;
+        self.set_coordinates(coordinates);
+        Ok(())
+    }
+
+    pub fn try_define_population(
+        &mut self,
+        name: impl Into<String>,
+        range: Range<u32>,
+    ) -> Result<&Population, Error> {
+        let name = name.into();
+        self.check_population(&name, &range)?;
+        Ok(self.define_population(name, range))
+    }
+
+    pub fn try_add_stimulus(&mut self, stimulus: Stimulus) -> Result<(), Error> {
+        self.check_stimulus(&stimulus)?;
+        self.add_stimulus(stimulus);
+        Ok(())
+    }
+
+    pub fn try_add_spike_monitor(
+        &mut self,
+        monitor: SpikeMonitor,
+    ) -> Result<SpikeMonitorId, Error> {
+        self.check_neurons(monitor.neurons().unwrap_or_default())?;
+        Ok(self.add_spike_monitor(monitor))
+    }
+
+    pub fn try_add_state_monitor(
+        &mut self,
+        monitor: StateMonitor,
+    ) -> Result<StateMonitorId, Error> {
+        self.check_neurons(monitor.neurons())?;
+        Ok(self.add_state_monitor(monitor))
+    }
+}
+
+impl Population {
+    /// [`neuron`](Population::neuron), with [`Error::InvalidNeuron`] for a
+    /// `k` past the end of the population.
+    pub fn try_neuron(&self, k: usize) -> Result<u32, Error> {
+        if k < self.len() {
+            Ok(self.neuron(k))
+        } else {
+            Err(Error::InvalidNeuron {
+                index: k.try_into().unwrap_or(u32::MAX),
+                count: self.len(),
+            })
+        }
+    }
+}
+
+impl Model {
+    pub fn try_alpha(&self, neuron: u32) -> Result<f32, Error> {
+        self.check_neuron(neuron)?;
+        Ok(self.alpha(neuron))
+    }
+
+    pub fn try_set_alpha(&mut self, neuron: u32, alpha: f32) -> Result<(), Error> {
+        self.check_neuron(neuron)?;
+        self.set_alpha(neuron, alpha);
+        Ok(())
+    }
+
+    pub fn try_refrac_len(&self, neuron: u32) -> Result<u16, Error> {
+        self.check_neuron(neuron)?;
+        Ok(self.refrac_len(neuron))
+    }
+
+    pub fn try_set_refrac_len(&mut self, neuron: u32, refrac_len: u16) -> Result<(), Error> {
+        self.check_neuron(neuron)?;
+        self.set_refrac_len(neuron, refrac_len);
+        Ok(())
+    }
+
+    pub fn try_v_th(&self, neuron: u32) -> Result<f32, Error> {
+        self.check_neuron(neuron)?;
+        Ok(self.v_th(neuron))
+    }
+
+    pub fn try_set_v_th(&mut self, neuron: u32, v_th: f32) -> Result<(), Error> {
+        self.check_neuron(neuron)?;
+        self.set_v_th(neuron, v_th);
+        Ok(())
+    }
+
+    pub fn try_v_reset(&self, neuron: u32) -> Result<f32, Error> {
+        self.check_neuron(neuron)?;
+        Ok(self.v_reset(neuron))
+    }
+
+    pub fn try_set_v_reset(&mut self, neuron: u32, v_reset: f32) -> Result<(), Error> {
+        self.check_neuron(neuron)?;
+        self.set_v_reset(neuron, v_reset);
+        Ok(())
+    }
+}
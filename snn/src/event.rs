@@ -0,0 +1,276 @@
+//! Event-driven simulation of LIF networks.
+//!
+//! [`Model::run_event_driven`] advances the same model as [`Model::tick`] but
+//! only visits neurons on ticks where something happens to them: a spike
+//! arrives, a stimulus or bias drives them, they reset after a spike, or their
+//! refractory period ends above threshold. Spike deliveries wait in a priority
+//! queue ordered by arrival tick. A neuron left alone catches up on its leak
+//! when it is next visited: the refractory countdown in one step, the
+//! potential in the clock-driven engine's floating-point steps until it stops
+//! changing, so both engines produce the same spike trains and potentials.
+//!
+//! Work per tick grows with the number of events instead of the network size,
+//! except for STDP, which still updates the traces of every neuron each tick.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::sync::Arc;
+
+use crate::{Model, NeuronModel};
+
+/// Pending deliveries and wake-ups of a run.
+struct Queue {
+    /// (arrival tick, arm order, synapse). Arm order keeps the summation order
+    /// of deliveries due on the same tick equal to the clock-driven engine.
+    deliveries: BinaryHeap<Reverse<(u64, u64, u32)>>,
+    /// (tick, neuron) on which a neuron may fire without input
+    wakes: BinaryHeap<Reverse<(u64, u32)>>,
+    armed: u64,
+}
+
+impl Queue {
+    fn deliver(&mut self, arrival: u64, synapse: u32) {
+        self.deliveries
+            .push(Reverse((arrival, self.armed, synapse)));
+        self.armed += 1;
+    }
+}
+
+/// Neurons visited on the current tick, each once.
+struct Touched {
+    neurons: Vec<u32>,
+    /// Tick each neuron was last added on
+    stamp: Vec<u64>,
+    time: u64,
+}
+
+impl Touched {
+    fn add(&mut self, neuron: u32) {
+        let stamp = &mut self.stamp[neuron as usize];
+        if *stamp != self.time {
+            *stamp = self.time;
+            self.neurons.push(neuron);
+        }
+    }
+}
+
+impl Model {
+    /// Simulate `ticks` steps event by event. The result is the same as
+    /// calling [`tick`](Self::tick) that many times, monitors included, but
+    /// quiet neurons cost nothing.
+    pub fn run_event_driven(&mut self, ticks: u64) {
+        self.compact();
+        let n = self.neuron_count();
+        let start = self.state.time;
+
+        // Driven every tick, or able to fire without input at any time
+        let active: Vec<u32> = (0..n)
+            .filter(|&i| self.bias[i] != 0.0 || !self.state.neurons.quiescent(i))
+            .map(|i| i as u32)
+            .collect();
+
+        let mut queue = Queue {
+            deliveries: BinaryHeap::new(),
+            wakes: BinaryHeap::new(),
+            armed: 0,
+        };
+        let ring_len = self.state.pending.len() as u64;
+        for wait in 1..ring_len {
+            let slot = ((start + wait) % ring_len) as usize;
+            for synapse in std::mem::take(&mut self.state.pending[slot]) {
+                queue.deliver(start + wait, synapse);
+            }
+        }
+        for i in 0..n {
+            let wake = if self.state.spiked[i] || self.state.input[i] != 0.0 {
+                Some(start + 1)
+            } else {
+                self.state.neurons.next_spontaneous(i, start)
+            };
+            if let Some(tick) = wake {
+                queue.wakes.push(Reverse((tick, i as u32)));
+            }
+        }
+
+        // Tick up to which each neuron's state is current
+        let mut last = vec![start; n];
+        let mut touched = Touched {
+            neurons: Vec::new(),
+            stamp: vec![start; n],
+            time: start,
+        };
+        let mut due = Vec::new();
+        let mut fired: Vec<u32> = Vec::new();
+
+        for _ in 0..ticks {
+            self.state.time += 1;
+            let time = self.state.time;
+            touched.time = time;
+            touched.neurons.clear();
+
+            // Everything that changes a neuron on this tick
+            for &i in fired.iter().chain(&active) {
+                touched.add(i);
+            }
+            while let Some(&Reverse((tick, i))) = queue.wakes.peek()
+                && tick == time
+            {
+                queue.wakes.pop();
+                touched.add(i);
+            }
+            due.clear();
+            while let Some(&Reverse((arrival, _, j))) = queue.deliveries.peek()
+                && arrival == time
+            {
+                queue.deliveries.pop();
+                due.push(j);
+                touched.add(self.topology.receiver[j as usize]);
+            }
+            self.apply_stimuli(|i| touched.add(i));
+
+            // 1) Catch up on the leak, then reset or integrate as `tick` does
+            for &i in &touched.neurons {
+                let i = i as usize;
+                self.state.neurons.idle(i, time - 1 - last[i]);
+                if self.state.spiked[i] {
+                    self.state.neurons.reset(i);
+                    self.state.spiked[i] = false;
+                } else {
+                    self.state
+                        .neurons
+                        .integrate(i, self.state.input[i] + self.bias[i]);
+                }
+                self.state.input[i] = 0.0;
+                last[i] = time;
+            }
+
+            // 2) Deliver in arm order
+            for &j in &due {
+                let j = j as usize;
+                self.state
+                    .neurons
+                    .receive(self.topology.receiver[j] as usize, self.topology.weight[j]);
+                self.state.in_flight[j] -= 1;
+            }
+
+            // 3) Only visited neurons can fire, in index order like `tick`
+            touched.neurons.sort_unstable();
+            fired.clear();
+            for &i in &touched.neurons {
+                let i = i as usize;
+                if !self.state.neurons.spike(i) {
+                    if let Some(tick) = self.state.neurons.next_spontaneous(i, time) {
+                        queue.wakes.push(Reverse((tick, i as u32)));
+                    }
+                    continue;
+                }
+                self.state.spiked[i] = true;
+                fired.push(i as u32);
+                for j in self.outgoing(i as u32) {
+                    queue.deliver(time + self.topology.delay[j] as u64, j as u32);
+                    self.state.in_flight[j] += 1;
+                }
+            }
+
+            // 4) Learn from this tick's spikes
+            if let Some(plasticity) = &mut self.plasticity {
+                let topology = Arc::make_mut(&mut self.topology);
+                plasticity.update(
+                    &self.state.spiked,
+                    &topology.out_offset,
+                    &topology.receiver,
+                    &mut topology.weight,
+                    self.dale.then_some(&self.kinds),
+                );
+            }
+
+            for i in self.monitors.sampled(time) {
+                let i = i as usize;
+                self.state.neurons.idle(i, time - last[i]);
+                last[i] = time;
+            }
+            self.monitors
+                .record_fired(time, &fired, self.state.neurons.v());
+        }
+
+        // Leave the model as `tick` would have
+        for (i, &last) in last.iter().enumerate() {
+            self.state.neurons.idle(i, self.state.time - last);
+        }
+        while let Some(Reverse((arrival, _, synapse))) = queue.deliveries.pop() {
+            self.state.pending[(arrival % ring_len) as usize].push(synapse);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::topology::tests::random_network;
+    use crate::{
+        Model, Recording, SpikeMonitor, SpikeMonitorId, StateMonitor, StateMonitorId, Stimulus,
+        Waveform,
+    };
+
+    /// A network with delays, bias, stimuli and a monitor of each kind.
+    fn monitored_network(seed: u64) -> (Model, SpikeMonitorId, StateMonitorId) {
+        let mut model = random_network(80, 0.06, -0.2..0.7, 7, seed);
+        model.set_bias(3, 0.15);
+        model.add_stimulus(Stimulus::new(
+            0..8,
+            Waveform::PulseTrain {
+                amplitude: 1.5,
+                period: 25,
+                width: 2,
+            },
+            5,
+            300,
+        ));
+        model.add_stimulus(Stimulus::new(
+            [40, 41, 42],
+            Waveform::Sine {
+                amplitude: 0.4,
+                period: 30.0,
+                phase: 0.0,
+                offset: 0.1,
+            },
+            50,
+            120,
+        ));
+        let spikes = model.add_spike_monitor(SpikeMonitor::all(Recording::unbounded()));
+        let states =
+            model.add_state_monitor(StateMonitor::new([0, 10, 41], 3, Recording::unbounded()));
+        (model, spikes, states)
+    }
+
+    /// Event-driven runs match clock-driven ones to the bit.
+    #[test]
+    fn matches_tick() {
+        for seed in 0..4 {
+            let (mut clocked, spikes, states) = monitored_network(seed);
+            let (mut events, ..) = monitored_network(seed);
+            for _ in 0..400 {
+                clocked.tick();
+            }
+            events.run_event_driven(150);
+            events.run_event_driven(250);
+
+            assert_eq!(clocked.time(), events.time());
+            assert_eq!(clocked.spiked(), events.spiked());
+            assert_eq!(clocked.synapse_states(), events.synapse_states());
+            let train = |m: &Model| m.spike_monitor(spikes).unwrap().to_vec();
+            assert!(train(&clocked).len() > 100);
+            assert_eq!(train(&clocked), train(&events));
+            let bits = |vs: &[f32]| vs.iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+            assert_eq!(bits(clocked.neuron_vs()), bits(events.neuron_vs()));
+            let samples = |m: &Model| {
+                let monitor = m.state_monitor(states).unwrap();
+                monitor
+                    .to_vec()
+                    .into_iter()
+                    .map(|(t, vs)| (t, bits(&vs)))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(samples(&clocked), samples(&events));
+        }
+    }
+}
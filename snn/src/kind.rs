@@ -0,0 +1,79 @@
+//! Excitatory and inhibitory neurons, and Dale's law.
+//!
+//! Every neuron is tagged with a [`NeuronKind`], excitatory unless set
+//! otherwise. While Dale's law is enforced the sign of each synapse follows the
+//! kind of its presynaptic neuron: weights given to the model are taken as
+//! magnitudes, and plasticity bounds magnitudes to `[w_min, w_max]`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Model, NeuronModel};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum NeuronKind {
+    #[default]
+    Excitatory,
+    Inhibitory,
+}
+
+impl NeuronKind {
+    /// Sign of the outgoing synapses under Dale's law.
+    pub fn sign(self) -> f32 {
+        match self {
+            Self::Excitatory => 1.0,
+            Self::Inhibitory => -1.0,
+        }
+    }
+}
+
+impl<N: NeuronModel> Model<N> {
+    pub fn kind(&self, neuron: u32) -> NeuronKind {
+        self.kinds[neuron as usize]
+    }
+
+    pub fn kinds(&self) -> &[NeuronKind] {
+        &self.kinds
+    }
+
+    /// Retag a neuron. Under Dale's law its outgoing synapses flip to the new sign.
+    pub fn set_kind(&mut self, neuron: u32, kind: NeuronKind) {
+        self.kinds[neuron as usize] = kind;
+        if self.dale {
+            self.apply_dale(neuron as usize);
+        }
+    }
+
+    /// Keep the sign of every synapse equal to that of its presynaptic
+    /// neuron's kind from now on. Synapses of the wrong sign flip.
+    pub fn enforce_dale(&mut self) {
+        self.dale = true;
+        for neuron in 0..self.neuron_count() {
+            self.apply_dale(neuron);
+        }
+    }
+
+    /// Stop enforcing Dale's law, weights keep their current values.
+    pub fn relax_dale(&mut self) {
+        self.dale = false;
+    }
+
+    pub fn dale_enforced(&self) -> bool {
+        self.dale
+    }
+
+    fn apply_dale(&mut self, neuron: usize) {
+        let sign = self.kinds[neuron].sign();
+        let range = self.outgoing(neuron as u32);
+        for w in &mut self.topology_mut().weight[range] {
+            *w = sign * w.abs();
+        }
+    }
+
+    /// Presynaptic neuron of a synapse.
+    pub(crate) fn presynaptic(&self, synapse: u32) -> usize {
+        self.topology
+            .out_offset
+            .partition_point(|&start| start <= synapse)
+            - 1
+    }
+}
@@ -0,0 +1,317 @@
+//! Regular lattices in 2D and 3D, with open or periodic boundaries.
+//!
+//! Every generator records the lattice coordinates of its neurons, with unit
+//! spacing between nearest neighbours, so they can be drawn without a layout.
+//! Weights may depend on the distance between the two neurons; under
+//! periodic boundaries that is the distance across the wrap. The coordinates
+//! do not record the wrap, so [`Model::synapse_lengths`] and
+//! [`Model::set_delays_by_distance`] see wrapping synapses as spanning the
+//! whole lattice.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{DEFAULT_SYNAPSE_DELAY, DistanceWeight, Lif, LifParams, Model};
+
+/// What happens at the edges of a lattice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Boundary {
+    /// Edge neurons have fewer neighbours
+    Open,
+    /// Edges wrap around, a torus in 2D
+    Periodic,
+}
+
+/// Which neurons of a square or cubic lattice a neuron connects to.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Neighbourhood {
+    /// Manhattan distance up to `r`, `VonNeumann(1)` are the orthogonal neighbours
+    VonNeumann(u32),
+    /// Chebyshev distance up to `r`, `Moore(1)` includes the diagonals
+    Moore(u32),
+    /// Euclidean distance up to the radius
+    Radius(f32),
+}
+
+impl Neighbourhood {
+    /// Largest offset along one axis
+    fn reach(&self) -> i64 {
+        match *self {
+            Self::VonNeumann(r) | Self::Moore(r) => r as i64,
+            Self::Radius(r) => r.max(0.0).floor() as i64,
+        }
+    }
+
+    fn contains(&self, offset: &[i64]) -> bool {
+        match *self {
+            Self::VonNeumann(r) => offset.iter().map(|d| d.unsigned_abs()).sum::<u64>() <= r as u64,
+            Self::Moore(r) => offset.iter().all(|d| d.unsigned_abs() <= r as u64),
+            Self::Radius(r) => distance(offset) <= r,
+        }
+    }
+}
+
+fn distance(offset: &[i64]) -> f32 {
+    offset.iter().map(|&d| (d * d) as f32).sum::<f32>().sqrt()
+}
+
+impl Model {
+    /// 2D grid with periodic edges, each neuron connected to its 4 orthogonal
+    /// neighbours. Neuron index is r*cols + c.
+    pub fn torus(rows: usize, cols: usize) -> Self {
+        Self::lattice_2d(
+            rows,
+            cols,
+            Neighbourhood::VonNeumann(1),
+            Boundary::Periodic,
+            DistanceWeight::default(),
+        )
+    }
+
+    /// Square lattice of size `rows × cols`, neuron index is r*cols + c,
+    /// placed at (c, r).
+    pub fn lattice_2d(
+        rows: usize,
+        cols: usize,
+        neighbourhood: Neighbourhood,
+        boundary: Boundary,
+        weights: DistanceWeight,
+    ) -> Self {
+        Self::cubic(cols, rows, 1, neighbourhood, boundary, weights)
+    }
+
+    /// Cubic lattice of size `nx × ny × nz`, neuron index is (z*ny + y)*nx + x,
+    /// placed at (x, y, z).
+    pub fn cubic(
+        nx: usize,
+        ny: usize,
+        nz: usize,
+        neighbourhood: Neighbourhood,
+        boundary: Boundary,
+        weights: DistanceWeight,
+    ) -> Self {
+        let size = [nx as i64, ny as i64, nz as i64];
+        let reach = neighbourhood.reach();
+        let mut offsets = Vec::new();
+        for dz in -reach..=reach {
+            for dy in -reach..=reach {
+                for dx in -reach..=reach {
+                    let offset = [dx, dy, dz];
+                    if offset != [0, 0, 0] && neighbourhood.contains(&offset) {
+                        offsets.push(offset);
+                    }
+                }
+            }
+        }
+        // Nearest first, so a site reached twice across the wrap keeps the short way
+        offsets.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+
+        let n = nx * ny * nz;
+        let mut coordinates = Vec::with_capacity(n);
+        let mut builder = LatticeBuilder::new(n, offsets.len());
+        for z in 0..size[2] {
+            for y in 0..size[1] {
+                for x in 0..size[0] {
+                    coordinates.push([x as f32, y as f32, z as f32]);
+                    for offset in &offsets {
+                        let target = [x + offset[0], y + offset[1], z + offset[2]];
+                        let Some([tx, ty, tz]) = place(target, size, boundary) else {
+                            continue;
+                        };
+                        let post = ((tz * size[1] + ty) * size[0] + tx) as u32;
+                        builder.connect(post, weights.weight(distance(offset)));
+                    }
+                    builder.close_neuron();
+                }
+            }
+        }
+        builder.build(coordinates)
+    }
+
+    /// Hexagonal lattice of `rows × cols`, every other row shifted right by half
+    /// a spacing. Neurons connect to all others within `radius` hexagon steps,
+    /// 6 per step. Neuron index is r*cols + c.
+    ///
+    /// Panics if the boundary is periodic and `rows` is odd, the shifted rows
+    /// wouldn't line up across the wrap.
+    pub fn hex(
+        rows: usize,
+        cols: usize,
+        radius: u32,
+        boundary: Boundary,
+        weights: DistanceWeight,
+    ) -> Self {
+        assert!(
+            boundary == Boundary::Open || rows.is_multiple_of(2),
+            "a periodic hex lattice needs an even number of rows, got {rows}"
+        );
+        let size = [cols as i64, rows as i64, 1];
+        let radius = radius as i64;
+        let position = |row: i64, col: i64| {
+            [
+                col as f32 + 0.5 * (row & 1) as f32,
+                row as f32 * 3f32.sqrt() * 0.5,
+                0.0,
+            ]
+        };
+
+        // Axial offsets (dq, dr) within `radius` steps, nearest first
+        let mut offsets = Vec::new();
+        for dr in -radius..=radius {
+            for dq in (-radius).max(-dr - radius)..=radius.min(-dr + radius) {
+                if (dq, dr) != (0, 0) {
+                    offsets.push((dq, dr));
+                }
+            }
+        }
+        offsets.sort_by_key(|&(dq, dr)| dq.abs() + dr.abs() + (dq + dr).abs());
+
+        let n = rows * cols;
+        let mut coordinates = Vec::with_capacity(n);
+        let mut builder = LatticeBuilder::new(n, offsets.len());
+        for row in 0..size[1] {
+            for col in 0..size[0] {
+                let here = position(row, col);
+                coordinates.push(here);
+                // Axial column, the offset column undoes the shift of odd rows
+                let q = col - (row - (row & 1)) / 2;
+                for &(dq, dr) in &offsets {
+                    let r1 = row + dr;
+                    let c1 = q + dq + (r1 - (r1 & 1)) / 2;
+                    let Some([tc, tr, _]) = place([c1, r1, 0], size, boundary) else {
+                        continue;
+                    };
+                    let there = position(r1, c1);
+                    let d = ((there[0] - here[0]).powi(2) + (there[1] - here[1]).powi(2)).sqrt();
+                    builder.connect((tr * size[0] + tc) as u32, weights.weight(d));
+                }
+                builder.close_neuron();
+            }
+        }
+        builder.build(coordinates)
+    }
+}
+
+/// Lattice site of `target`, wrapped under periodic boundaries,
+/// `None` if it falls off an open edge.
+fn place(target: [i64; 3], size: [i64; 3], boundary: Boundary) -> Option<[i64; 3]> {
+    let mut placed = [0; 3];
+    for axis in 0..3 {
+        placed[axis] = match boundary {
+            Boundary::Open if !(0..size[axis]).contains(&target[axis]) => return None,
+            Boundary::Open => target[axis],
+            Boundary::Periodic => target[axis].rem_euclid(size[axis]),
+        };
+    }
+    Some(placed)
+}
+
+/// Collects synapses in CSR order, one neuron at a time. Drops self-loops and
+/// repeats, which wrapping creates on lattices smaller than the neighbourhood.
+struct LatticeBuilder {
+    out_offset: Vec<u32>,
+    receiver: Vec<u32>,
+    weight: Vec<f32>,
+}
+
+impl LatticeBuilder {
+    fn new(neurons: usize, degree: usize) -> Self {
+        let mut out_offset = Vec::with_capacity(neurons + 1);
+        out_offset.push(0);
+        Self {
+            out_offset,
+            receiver: Vec::with_capacity(neurons * degree),
+            weight: Vec::with_capacity(neurons * degree),
+        }
+    }
+
+    fn connect(&mut self, post: u32, weight: f32) {
+        let pre = (self.out_offset.len() - 1) as u32;
+        let start = *self.out_offset.last().unwrap() as usize;
+        if post == pre || self.receiver[start..].contains(&post) {
+            return;
+        }
+        self.receiver.push(post);
+        self.weight.push(weight);
+    }
+
+    fn close_neuron(&mut self) {
+        self.out_offset.push(self.receiver.len() as u32);
+    }
+
+    fn build(self, coordinates: Vec<[f32; 3]>) -> Model {
+        let n = self.out_offset.len() - 1;
+        let delay = vec![DEFAULT_SYNAPSE_DELAY; self.receiver.len()];
+        let mut model = Model::from_csr(
+            Lif::uniform(n, LifParams::default()),
+            self.out_offset,
+            self.receiver,
+            self.weight,
+            delay,
+        );
+        model.coordinates = Some(coordinates);
+        model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Boundary, Neighbourhood};
+    use crate::topology::tests::assert_simple;
+    use crate::{DistanceWeight, Model};
+
+    /// Synapse count of every neuron.
+    fn out_degrees(model: &Model) -> Vec<usize> {
+        let topology = model.topology();
+        (0..model.neuron_count() as u32)
+            .map(|i| topology.outgoing(i).len())
+            .collect()
+    }
+
+    /// Every neuron of a torus has its 4 orthogonal neighbours.
+    #[test]
+    fn torus_has_degree_4() {
+        let model = Model::torus(5, 7);
+        assert!(out_degrees(&model).iter().all(|&d| d == 4));
+        let topology = model.topology();
+        let mut corner = topology.receiver[topology.outgoing(0)].to_vec();
+        corner.sort_unstable();
+        assert_eq!(corner, [1, 6, 7, 28]);
+    }
+
+    /// Wrapping on lattices smaller than the neighbourhood makes no
+    /// self-loops or repeats.
+    #[test]
+    fn lattices_make_simple_graphs() {
+        let weights = DistanceWeight::default();
+        for boundary in [Boundary::Open, Boundary::Periodic] {
+            for neighbourhood in [
+                Neighbourhood::VonNeumann(2),
+                Neighbourhood::Moore(1),
+                Neighbourhood::Radius(1.5),
+            ] {
+                assert_simple(&Model::lattice_2d(2, 3, neighbourhood, boundary, weights));
+                assert_simple(&Model::cubic(3, 2, 4, neighbourhood, boundary, weights));
+            }
+            assert_simple(&Model::hex(2, 3, 2, boundary, weights));
+        }
+        assert_simple(&Model::torus(1, 2));
+    }
+
+    /// Interior hexagons have 6 neighbours at radius 1 and 18 at radius 2.
+    #[test]
+    fn hex_neighbours() {
+        let weights = DistanceWeight::default();
+        let model = Model::hex(6, 6, 1, Boundary::Open, weights);
+        let degrees = out_degrees(&model);
+        for row in 1..5 {
+            for col in 1..5 {
+                assert_eq!(degrees[row * 6 + col], 6, "({row}, {col})");
+            }
+        }
+        assert!(degrees.iter().all(|&d| (2..=6).contains(&d)));
+        let periodic = Model::hex(6, 6, 1, Boundary::Periodic, weights);
+        assert!(out_degrees(&periodic).iter().all(|&d| d == 6));
+        let wide = Model::hex(8, 8, 2, Boundary::Periodic, weights);
+        assert!(out_degrees(&wide).iter().all(|&d| d == 18));
+    }
+}
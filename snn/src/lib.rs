@@ -1,35 +1,97 @@
+pub mod batch;
+pub mod builder;
+pub mod csc;
+pub mod decoding;
+pub mod encoding;
+pub mod error;
+pub mod event;
+pub mod kind;
+pub mod lattice;
+pub mod monitor;
+pub mod neuron;
+pub mod parallel;
+pub mod param;
+pub mod persist;
+pub mod plasticity;
+pub mod population;
 pub mod render;
+pub mod reservoir;
+pub mod spatial;
+pub mod state;
+pub mod stimulus;
+pub mod structure;
+pub mod topology;
+pub mod validate;
 
+pub use batch::Batch;
+pub use builder::{ModelBuilder, NeuronId, NeuronParams};
+pub use csc::ReverseIndex;
+pub use decoding::{
+    Decision, Decoder, FirstSpikeDecoder, FitError, LinearReadout, MaxPotentialDecoder,
+    SpikeCountDecoder,
+};
+pub use encoding::{
+    CurrentEncoder, Encoder, LatencyEncoder, PoissonEncoder, PopulationEncoder, RateEncoder,
+};
+pub use error::Error;
+pub use kind::NeuronKind;
+pub use lattice::{Boundary, Neighbourhood};
+pub use monitor::{Recording, SpikeMonitor, SpikeMonitorId, StateMonitor, StateMonitorId};
+pub use neuron::{
+    AdEx, AdExParams, Izhikevich, IzhikevichParams, Lif, LifParams, NeuronModel, NeuronPart,
+};
+pub use param::PerNeuron;
+pub use persist::{Format, PersistError};
+pub use plasticity::{RewardModulation, Stdp, StdpRule};
+pub use population::{Connectivity, Population, Projection};
+pub use reservoir::{Evaluation, Reservoir, Sequence, SpikeFilter};
+pub use spatial::{DistanceDelay, DistanceProfile, DistanceWeight};
+pub use state::{SimState, Topology};
+pub use stimulus::{Stimulus, Waveform};
+pub use structure::{NeuronKey, SynapseKey};
+pub use topology::{Balanced, WeightDist};
+pub use validate::{ValidationReport, Violation};
+
+use std::sync::Arc;
+
+use monitor::Monitors;
+use parallel::Threads;
+use plasticity::Plasticity;
+use structure::Structure;
+
+const DEFAULT_ALPHA: f32 = 0.1;
+const DEFAULT_REFRAC_LEN: u16 = 2;
 const DEFAULT_SYNAPSE_WEIGHT: f32 = 0.5;
+const DEFAULT_SYNAPSE_DELAY: u16 = 1;
 
 // SoA
-pub struct Model {
-    // ----------------------- Leaky Integrate-And-Fire Neurons
-    /// Membrane potential v_t
-    v: Vec<f32>,
-    /// Leak factor alpha = dt / tau, 0 < alpha <= 1
-    alpha: f32,
-    /// Firing threshold
-    v_th: Vec<f32>,
-    /// Reset value after spike
-    v_reset: Vec<f32>,
-    /// Remaining refractory steps; 0 means active
-    refrac: Vec<u16>,
-    /// Fixed refractory length in steps
-    refrac_len: u16,
-    /// Spiked flag for each neuron, so we can render neuron charges
-    spiked: Vec<bool>,
-
-    // ----------------------- CSR-Synapses
-    /// start/end in "receiver" etc for neuron i is out_offset[i]..out_offset[i+1]
-    out_offset: Vec<u32>,
-    /// Receiver Neuron
-    receiver: Vec<u32>,
-    /// Synapse weight
-    weight: Vec<f32>,
-    /// Synaptic fired flag (true means: deliver weight this tick, then clear)
-    state: Vec<u32>,
-    // ----------------------- Other simulation state
+pub struct Model<N: NeuronModel = Lif> {
+    /// Synapses, shared with forks of the model until one of them changes it
+    topology: Arc<Topology>,
+    /// Neurons, spikes in flight and time
+    state: SimState<N>,
+
+    // ----------------------- Configuration
+    /// Persistent external current
+    bias: Vec<f32>,
+    /// Excitatory or inhibitory, decides synapse signs under Dale's law
+    kinds: Vec<NeuronKind>,
+    /// Whether synapse signs follow the kind of their presynaptic neuron
+    dale: bool,
+    /// STDP traces, present while learning is enabled
+    plasticity: Option<Plasticity>,
+    /// Attached recorders, filled at the end of every tick
+    monitors: Monitors,
+    /// Scheduled currents, dropped once they are over
+    stimuli: Vec<Stimulus>,
+    /// Position of each neuron, if the generator gave the network a shape
+    coordinates: Option<Vec<[f32; 3]>>,
+    /// Named index ranges
+    populations: Vec<Population>,
+    /// Worker threads, `None` ticks serially
+    threads: Option<Threads>,
+    /// Stable keys and pending edits, `None` until the network is first edited
+    structure: Option<Structure>,
 }
 
 impl Default for Model {
@@ -40,30 +102,17 @@ impl Default for Model {
 
 impl Model {
     pub fn empty() -> Self {
-        // alpha: small leak per step
-        // refrac_len: short refractory period
-        Self {
-            v: Vec::new(),
-            alpha: 0.1,
-            v_th: Vec::new(),
-            v_reset: Vec::new(),
-            refrac: Vec::new(),
-            refrac_len: 2,
-            spiked: Vec::new(),
-            out_offset: vec![0],
-            receiver: Vec::new(),
-            weight: Vec::new(),
-            state: Vec::new(),
-        }
+        Self::from_csr(Lif::new(), vec![0], Vec::new(), Vec::new(), Vec::new())
     }
 
     /// Build a 1D chain: 0 → 1 → 2 → ... → (n-1).
     /// Each neuron i (except the last) has exactly one outgoing synapse to i+1.
+    /// Neuron i is placed at (i, 0).
     pub fn line(neurons: usize) -> Self {
         let mut out_offset = Vec::with_capacity(neurons + 1);
         let mut receiver = Vec::with_capacity(neurons.saturating_sub(1));
         let mut weight = Vec::with_capacity(neurons.saturating_sub(1));
-        let mut state = Vec::with_capacity(neurons.saturating_sub(1));
+        let mut delay = Vec::with_capacity(neurons.saturating_sub(1));
 
         // CSR prefix
         out_offset.push(0);
@@ -71,28 +120,26 @@ impl Model {
             if i + 1 < neurons {
                 receiver.push((i + 1) as u32);
                 weight.push(DEFAULT_SYNAPSE_WEIGHT);
-                state.push(0);
+                delay.push(DEFAULT_SYNAPSE_DELAY);
             }
             out_offset.push(receiver.len() as u32);
         }
 
-        Self {
-            v: vec![0.0; neurons],
-            v_th: vec![1.0; neurons],
-            v_reset: vec![0.0; neurons],
-            refrac: vec![0; neurons],
-            spiked: vec![false; neurons],
+        let mut model = Self::from_csr(
+            Lif::uniform(neurons, LifParams::default()),
             out_offset,
             receiver,
             weight,
-            state,
-            ..Default::default()
-        }
+            delay,
+        );
+        model.coordinates = Some((0..neurons).map(|i| [i as f32, 0.0, 0.0]).collect());
+        model
     }
 
     /// Build a 2D grid of size `rows × cols`.
     /// Neuron index is r*cols + c.
     /// Each neuron connects only to its 4 orthogonal neighbours if they exist.
+    /// Neuron r*cols + c is placed at (c, r).
     pub fn grid(rows: usize, cols: usize) -> Self {
         let n = rows.saturating_mul(cols);
 
@@ -100,7 +147,7 @@ impl Model {
         let mut out_offset = Vec::with_capacity(n + 1);
         let mut receiver = Vec::with_capacity(n.saturating_mul(4));
         let mut weight = Vec::with_capacity(n.saturating_mul(4));
-        let mut state = Vec::with_capacity(n.saturating_mul(4));
+        let mut delay = Vec::with_capacity(n.saturating_mul(4));
 
         out_offset.push(0);
 
@@ -113,28 +160,28 @@ impl Model {
                     let up = (r - 1) * cols + c;
                     receiver.push(up as u32);
                     weight.push(DEFAULT_SYNAPSE_WEIGHT);
-                    state.push(0);
+                    delay.push(DEFAULT_SYNAPSE_DELAY);
                 }
                 // Down
                 if r + 1 < rows {
                     let down = (r + 1) * cols + c;
                     receiver.push(down as u32);
                     weight.push(DEFAULT_SYNAPSE_WEIGHT);
-                    state.push(0);
+                    delay.push(DEFAULT_SYNAPSE_DELAY);
                 }
                 // Left
                 if c > 0 {
                     let left = r * cols + (c - 1);
                     receiver.push(left as u32);
                     weight.push(DEFAULT_SYNAPSE_WEIGHT);
-                    state.push(0);
+                    delay.push(DEFAULT_SYNAPSE_DELAY);
                 }
                 // Right
                 if c + 1 < cols {
                     let right = r * cols + (c + 1);
                     receiver.push(right as u32);
                     weight.push(DEFAULT_SYNAPSE_WEIGHT);
-                    state.push(0);
+                    delay.push(DEFAULT_SYNAPSE_DELAY);
                 }
 
                 // Close CSR range for this neuron.
@@ -144,95 +191,532 @@ impl Model {
             }
         }
 
-        Self {
-            v: vec![0.0; n],
-            v_th: vec![1.0; n],
-            v_reset: vec![0.0; n],
-            refrac: vec![0; n],
-            spiked: vec![false; n],
+        let mut model = Self::from_csr(
+            Lif::uniform(n, LifParams::default()),
+            out_offset,
+            receiver,
+            weight,
+            delay,
+        );
+        model.coordinates = Some(
+            (0..n)
+                .map(|i| [(i % cols) as f32, (i / cols) as f32, 0.0])
+                .collect(),
+        );
+        model
+    }
+
+    pub fn alpha(&self, neuron: u32) -> f32 {
+        self.state.neurons.alpha(neuron as usize)
+    }
+
+    pub fn set_alpha(&mut self, neuron: u32, alpha: f32) {
+        self.state.neurons.set_alpha(neuron as usize, alpha);
+    }
+
+    /// Use the same leak factor for all neurons.
+    pub fn set_alpha_all(&mut self, alpha: f32) {
+        self.state.neurons.set_alpha_all(alpha);
+    }
+
+    pub fn refrac_len(&self, neuron: u32) -> u16 {
+        self.state.neurons.refrac_len(neuron as usize)
+    }
+
+    pub fn set_refrac_len(&mut self, neuron: u32, refrac_len: u16) {
+        self.state
+            .neurons
+            .set_refrac_len(neuron as usize, refrac_len);
+    }
+
+    /// Use the same refractory length for all neurons.
+    pub fn set_refrac_len_all(&mut self, refrac_len: u16) {
+        self.state.neurons.set_refrac_len_all(refrac_len);
+    }
+
+    pub fn v_th(&self, neuron: u32) -> f32 {
+        self.state.neurons.v_th(neuron as usize)
+    }
+
+    pub fn set_v_th(&mut self, neuron: u32, v_th: f32) {
+        self.state.neurons.set_v_th(neuron as usize, v_th);
+    }
+
+    pub fn set_v_th_all(&mut self, v_th: f32) {
+        self.state.neurons.set_v_th_all(v_th);
+    }
+
+    pub fn v_reset(&self, neuron: u32) -> f32 {
+        self.state.neurons.v_reset(neuron as usize)
+    }
+
+    pub fn set_v_reset(&mut self, neuron: u32, v_reset: f32) {
+        self.state.neurons.set_v_reset(neuron as usize, v_reset);
+    }
+
+    pub fn set_v_reset_all(&mut self, v_reset: f32) {
+        self.state.neurons.set_v_reset_all(v_reset);
+    }
+}
+
+impl<N: NeuronModel> Model<N> {
+    /// Assemble a model from a neuron population and synapses already laid out as CSR.
+    fn from_csr(
+        neurons: N,
+        out_offset: Vec<u32>,
+        receiver: Vec<u32>,
+        weight: Vec<f32>,
+        delay: Vec<u16>,
+    ) -> Self {
+        debug_assert_eq!(out_offset.len(), neurons.len() + 1);
+        let topology = Topology {
             out_offset,
             receiver,
             weight,
-            state,
-            ..Default::default()
+            delay,
+        };
+        Self::from_topology(Arc::new(topology), neurons)
+    }
+
+    /// A model at rest over synapses shared with other models.
+    ///
+    /// Panics if `neurons` has a different neuron count than the topology.
+    pub fn from_topology(topology: Arc<Topology>, neurons: N) -> Self {
+        let n = neurons.len();
+        Self {
+            state: SimState::new(&topology, neurons),
+            topology,
+            bias: vec![0.0; n],
+            kinds: vec![NeuronKind::Excitatory; n],
+            dale: false,
+            plasticity: None,
+            monitors: Monitors::default(),
+            stimuli: Vec::new(),
+            coordinates: None,
+            populations: Vec::new(),
+            threads: None,
+            structure: None,
+        }
+    }
+
+    /// Keep the topology but swap in different neuron dynamics.
+    ///
+    /// Panics if `neurons` has a different neuron count.
+    pub fn with_neurons<M: NeuronModel>(self, neurons: M) -> Model<M> {
+        assert_eq!(
+            neurons.len(),
+            self.state.neurons.len(),
+            "replacement population must have the same neuron count"
+        );
+        let state = self.state;
+        Model {
+            topology: self.topology,
+            state: SimState {
+                neurons,
+                spiked: state.spiked,
+                input: state.input,
+                in_flight: state.in_flight,
+                pending: state.pending,
+                time: state.time,
+            },
+            bias: self.bias,
+            kinds: self.kinds,
+            dale: self.dale,
+            plasticity: self.plasticity,
+            monitors: self.monitors,
+            stimuli: self.stimuli,
+            coordinates: self.coordinates,
+            populations: self.populations,
+            threads: self.threads,
+            structure: self.structure,
+        }
+    }
+
+    /// Another trial of the same network in the same state, sharing the
+    /// topology. Monitors, learning and worker threads are not carried over.
+    pub fn fork(&self) -> Self
+    where
+        N: Clone,
+    {
+        Self {
+            topology: Arc::clone(&self.topology),
+            state: self.state.clone(),
+            bias: self.bias.clone(),
+            kinds: self.kinds.clone(),
+            dale: self.dale,
+            plasticity: None,
+            monitors: Monitors::default(),
+            stimuli: self.stimuli.clone(),
+            coordinates: self.coordinates.clone(),
+            populations: self.populations.clone(),
+            threads: None,
+            structure: self.structure.clone(),
         }
     }
 
+    pub fn topology(&self) -> &Arc<Topology> {
+        &self.topology
+    }
+
+    /// Synapses for changing, copied first if other models share them.
+    fn topology_mut(&mut self) -> &mut Topology {
+        Arc::make_mut(&mut self.topology)
+    }
+
+    pub fn state(&self) -> &SimState<N> {
+        &self.state
+    }
+
+    /// Continue from a state taken from this model or one sharing its topology.
+    ///
+    /// Panics if the state belongs to a different network.
+    pub fn set_state(&mut self, state: SimState<N>) {
+        assert!(
+            state.fits(&self.topology),
+            "state does not fit the topology of the model"
+        );
+        self.state = state;
+    }
+
+    pub fn neurons(&self) -> &N {
+        &self.state.neurons
+    }
+
+    pub fn neurons_mut(&mut self) -> &mut N {
+        &mut self.state.neurons
+    }
+
     pub fn set_charge(&mut self, neuron: u32, charge: f32) {
-        self.v[neuron as usize] = charge;
+        self.state.neurons.v_mut()[neuron as usize] = charge;
     }
 
     pub fn get_charge(&self, neuron: u32) -> f32 {
-        self.v[neuron as usize]
+        self.state.neurons.v()[neuron as usize]
+    }
+
+    pub fn neuron_count(&self) -> usize {
+        self.state.neurons.len()
+    }
+
+    pub fn synapse_count(&self) -> usize {
+        self.topology.receiver.len()
     }
 
     /// Simulate the model for one time step.
     ///
     /// Order:
-    /// 1) leak membranes
-    /// 2) advance refractory counters
-    /// 3) deliver all synapses that fired in the previous step
-    /// 4) detect spikes and arm synapses for next step
+    /// 1) reset neurons that spiked on the previous step, integrate all others
+    ///    with their external current (input + bias + stimuli)
+    /// 2) deliver all synapses whose delay elapsed this step
+    /// 3) detect spikes and schedule deliveries on outgoing synapses
+    /// 4) apply STDP or accumulate eligibility if enabled
+    ///
+    /// Steps 1 to 3 run on several threads after [`set_threads`](Self::set_threads).
+    /// Structural edits waiting for [`compact`](Self::compact) are applied first.
     pub fn tick(&mut self) {
-        // 1) Reset spiked neurons
-        for i in 0..self.spiked.len() {
-            if self.spiked[i] {
-                self.v[i] = self.v_reset[i];
-                self.refrac[i] = self.refrac_len;
-                self.spiked[i] = false;
-            } else {
-                // Leak membrane potential
-                self.v[i] *= 1.0 - self.alpha;
-            }
+        self.compact();
+        self.state.time += 1;
+        self.apply_stimuli(|_| {});
+        if !self.step_parallel() {
+            self.step_serial();
         }
 
-        // 2) Update refrac for neurons
-        for r in &mut self.refrac {
-            *r = r.saturating_sub(1);
+        // 4) Learn from this tick's spikes
+        if let Some(plasticity) = &mut self.plasticity {
+            let topology = Arc::make_mut(&mut self.topology);
+            plasticity.update(
+                &self.state.spiked,
+                &topology.out_offset,
+                &topology.receiver,
+                &mut topology.weight,
+                self.dale.then_some(&self.kinds),
+            );
         }
 
-        // 3) Deliver pending synapses (one-tick delay)
-        for i in 0..self.state.len() {
-            if self.state[i] == 1 {
-                let recv = self.receiver[i] as usize;
-                self.v[recv] += self.weight[i];
-                // clear for next round
-                self.state[i] = 0;
+        self.monitors
+            .record(self.state.time, &self.state.spiked, self.state.neurons.v());
+    }
+
+    fn step_serial(&mut self) {
+        // 1) Reset spiked neurons, integrate the rest under external currents
+        for i in 0..self.state.spiked.len() {
+            if self.state.spiked[i] {
+                self.state.neurons.reset(i);
+                self.state.spiked[i] = false;
+            } else {
+                self.state
+                    .neurons
+                    .integrate(i, self.state.input[i] + self.bias[i]);
             }
         }
+        self.state.input.fill(0.0);
 
-        // 4) Update spikes and arm synapses for next tick
-        for i in 0..self.v.len() {
-            if self.refrac[i] > 0 {
-                continue;
-            }
-            if self.v[i] < self.v_th[i] {
+        // 2) Deliver pending synapses due this tick
+        let ring_len = self.state.pending.len() as u64;
+        let slot = (self.state.time % ring_len) as usize;
+        let mut due = std::mem::take(&mut self.state.pending[slot]);
+        for &j in &due {
+            let j = j as usize;
+            let recv = self.topology.receiver[j] as usize;
+            self.state.neurons.receive(recv, self.topology.weight[j]);
+            self.state.in_flight[j] -= 1;
+        }
+        // Keep the allocation of the slot around for later ticks
+        due.clear();
+        self.state.pending[slot] = due;
+
+        // 3) Update spikes and schedule deliveries
+        for i in 0..self.state.spiked.len() {
+            if !self.state.neurons.spike(i) {
                 continue;
             }
 
             // spike
-            self.spiked[i] = true;
+            self.state.spiked[i] = true;
 
-            // arm outgoing synapses to fire on the NEXT tick
-            let start = self.out_offset[i] as usize;
-            let end = self.out_offset[i + 1] as usize;
+            // arm outgoing synapses to deliver after their delay
+            let start = self.topology.out_offset[i] as usize;
+            let end = self.topology.out_offset[i + 1] as usize;
             for j in start..end {
-                self.state[j] = 1;
+                let arrival =
+                    ((self.state.time + self.topology.delay[j] as u64) % ring_len) as usize;
+                self.state.pending[arrival].push(j as u32);
+                self.state.in_flight[j] += 1;
             }
         }
     }
 
     pub fn neuron_vs(&self) -> &[f32] {
-        &self.v
+        self.state.neurons.v()
     }
 
+    /// Add `current` to the external input of `neuron` for the next tick only.
+    pub fn add_input(&mut self, neuron: u32, current: f32) {
+        self.state.input[neuron as usize] += current;
+    }
+
+    /// External input queued for the next tick.
+    pub fn inputs(&self) -> &[f32] {
+        &self.state.input
+    }
+
+    pub fn bias(&self, neuron: u32) -> f32 {
+        self.bias[neuron as usize]
+    }
+
+    /// Set a current that is applied to `neuron` on every tick.
+    pub fn set_bias(&mut self, neuron: u32, current: f32) {
+        self.bias[neuron as usize] = current;
+    }
+
+    pub fn set_bias_all(&mut self, current: f32) {
+        self.bias.fill(current);
+    }
+
+    /// Schedule a stimulus, in ticks of [`time`](Self::time).
+    /// Stimuli add up with each other and with the other inputs.
+    ///
+    /// Panics if a target does not exist or the waveform is malformed.
+    pub fn add_stimulus(&mut self, stimulus: Stimulus) {
+        if let Err(e) = self.check_stimulus(&stimulus) {
+            panic!("{e}");
+        }
+        self.stimuli.push(stimulus);
+    }
+
+    /// Stimuli that have not ended yet.
+    pub fn stimuli(&self) -> &[Stimulus] {
+        &self.stimuli
+    }
+
+    pub fn clear_stimuli(&mut self) {
+        self.stimuli.clear();
+    }
+
+    /// Add the current of every active stimulus to the input buffer,
+    /// calling `touched` with every neuron that receives some.
+    fn apply_stimuli(&mut self, mut touched: impl FnMut(u32)) {
+        if self.stimuli.is_empty() {
+            return;
+        }
+        let time = self.state.time;
+        self.stimuli.retain(|s| s.end() > time);
+        for stimulus in &self.stimuli {
+            let current = stimulus.current_at(time);
+            if current == 0.0 {
+                continue;
+            }
+            for &target in &stimulus.targets {
+                self.state.input[target as usize] += current;
+                touched(target);
+            }
+        }
+    }
+
+    /// Whether each neuron spiked on the latest tick.
+    pub fn spiked(&self) -> &[bool] {
+        &self.state.spiked
+    }
+
+    /// Number of spikes in flight for each synapse, 0 if none.
     pub fn synapse_states(&self) -> &[u32] {
-        &self.state
+        &self.state.in_flight
+    }
+
+    pub fn synapse_delays(&self) -> &[u16] {
+        &self.topology.delay
+    }
+
+    pub fn synapse_weights(&self) -> &[f32] {
+        &self.topology.weight
+    }
+
+    /// Under Dale's law only the magnitude of `weight` counts.
+    pub fn set_synapse_weight(&mut self, synapse: u32, weight: f32) {
+        let weight = if self.dale {
+            self.kinds[self.presynaptic(synapse)].sign() * weight.abs()
+        } else {
+            weight
+        };
+        self.topology_mut().weight[synapse as usize] = weight;
+    }
+
+    /// Postsynaptic neuron of each synapse.
+    pub fn synapse_receivers(&self) -> &[u32] {
+        &self.topology.receiver
+    }
+
+    /// Synapse indices leaving `neuron`.
+    pub fn outgoing(&self, neuron: u32) -> std::ops::Range<usize> {
+        let i = neuron as usize;
+        self.topology.out_offset[i] as usize..self.topology.out_offset[i + 1] as usize
+    }
+
+    /// Build the reverse (CSC) index of the current topology.
+    pub fn reverse_index(&self) -> ReverseIndex {
+        ReverseIndex::new(&self.topology.out_offset, &self.topology.receiver)
     }
 
-    /// Render this model using Graphviz' **neato** engine and return an SVG in-memory.
+    /// Start learning with pair-based STDP, replacing any previous configuration.
+    /// Traces start at zero.
+    pub fn enable_stdp(&mut self, stdp: Stdp) {
+        self.plasticity = Some(Plasticity::new(
+            stdp,
+            None,
+            &self.topology.out_offset,
+            &self.topology.receiver,
+        ));
+    }
+
+    /// Start reward-modulated learning, replacing any previous configuration.
+    /// Weights only change on [`deliver_reward`](Self::deliver_reward).
+    pub fn enable_reward_modulated_stdp(&mut self, stdp: Stdp, modulation: RewardModulation) {
+        self.plasticity = Some(Plasticity::new(
+            stdp,
+            Some(modulation),
+            &self.topology.out_offset,
+            &self.topology.receiver,
+        ));
+    }
+
+    /// Deliver a global reward (dopamine) signal, negative values punish.
+    /// Does nothing unless reward-modulated learning is enabled.
+    pub fn deliver_reward(&mut self, reward: f32) {
+        if let Some(plasticity) = &mut self.plasticity {
+            let topology = Arc::make_mut(&mut self.topology);
+            plasticity.reward(
+                reward,
+                &topology.out_offset,
+                &mut topology.weight,
+                self.dale.then_some(&self.kinds),
+            );
+        }
+    }
+
+    /// Eligibility trace of each synapse while reward-modulated learning is enabled.
+    pub fn eligibility(&self) -> Option<&[f32]> {
+        self.plasticity
+            .as_ref()
+            .and_then(|p| p.eligibility.as_ref())
+            .map(|e| e.trace.as_slice())
+    }
+
+    pub fn disable_stdp(&mut self) {
+        self.plasticity = None;
+    }
+
+    pub fn stdp(&self) -> Option<&Stdp> {
+        self.plasticity.as_ref().map(|p| &p.stdp)
+    }
+
+    /// Number of ticks simulated so far.
+    pub fn time(&self) -> u64 {
+        self.state.time
+    }
+
+    /// Position of each neuron, set by generators with a natural geometry
+    /// or by [`set_coordinates`](Self::set_coordinates).
+    pub fn coordinates(&self) -> Option<&[[f32; 3]]> {
+        self.coordinates.as_deref()
+    }
+
+    /// Attach a spike monitor, it records from the next tick on.
+    ///
+    /// Panics if a watched neuron does not exist.
+    pub fn add_spike_monitor(&mut self, monitor: SpikeMonitor) -> SpikeMonitorId {
+        if let Err(e) = self.check_neurons(monitor.neurons().unwrap_or_default()) {
+            panic!("{e}");
+        }
+        self.monitors.add_spike(monitor)
+    }
+
+    pub fn spike_monitor(&self, id: SpikeMonitorId) -> Option<&SpikeMonitor> {
+        self.monitors.spike(id)
+    }
+
+    pub fn spike_monitor_mut(&mut self, id: SpikeMonitorId) -> Option<&mut SpikeMonitor> {
+        self.monitors.spike_mut(id)
+    }
+
+    /// Detach a spike monitor and hand back what it recorded.
+    pub fn remove_spike_monitor(&mut self, id: SpikeMonitorId) -> Option<SpikeMonitor> {
+        self.monitors.remove_spike(id)
+    }
+
+    /// Attach a state monitor, it samples from the next tick on.
+    ///
+    /// Panics if a watched neuron does not exist.
+    pub fn add_state_monitor(&mut self, monitor: StateMonitor) -> StateMonitorId {
+        if let Err(e) = self.check_neurons(monitor.neurons()) {
+            panic!("{e}");
+        }
+        self.monitors.add_state(monitor)
+    }
+
+    pub fn state_monitor(&self, id: StateMonitorId) -> Option<&StateMonitor> {
+        self.monitors.state(id)
+    }
+
+    pub fn state_monitor_mut(&mut self, id: StateMonitorId) -> Option<&mut StateMonitor> {
+        self.monitors.state_mut(id)
+    }
+
+    /// Detach a state monitor and hand back what it recorded.
+    pub fn remove_state_monitor(&mut self, id: StateMonitorId) -> Option<StateMonitor> {
+        self.monitors.remove_state(id)
+    }
+
+    /// Render this model using Graphviz' **neato** engine and return a PNG in-memory.
     /// Requires the `graphviz-exec` feature and a `dot`/Graphviz installation.
-    pub fn to_neato_png(&self) -> std::io::Result<Vec<u8>> {
+    #[cfg(feature = "graphviz-exec")]
+    pub fn to_neato_png(&self) -> Result<Vec<u8>, Error> {
         render::to_neato_png(self)
     }
 }
+
+/// Allocate the delivery ring for the given synapse delays.
+/// One slot per possible delay plus the slot currently being delivered.
+fn pending_ring(delay: &[u16]) -> Vec<Vec<u32>> {
+    let max_delay = delay.iter().copied().max().unwrap_or(DEFAULT_SYNAPSE_DELAY);
+    vec![Vec::new(); max_delay as usize + 1]
+}
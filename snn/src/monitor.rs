@@ -0,0 +1,340 @@
+use std::collections::VecDeque;
+
+use crate::{Population, structure::REMOVED};
+
+/// Recorded samples, either kept in full or as a ring buffer of the newest ones.
+#[derive(Clone, Debug)]
+pub enum Recording<T> {
+    Unbounded(Vec<T>),
+    /// Drops the oldest sample once `capacity` is reached
+    Bounded {
+        buffer: VecDeque<T>,
+        capacity: usize,
+    },
+}
+
+impl<T> Recording<T> {
+    pub fn unbounded() -> Self {
+        Self::Unbounded(Vec::new())
+    }
+
+    pub fn bounded(capacity: usize) -> Self {
+        Self::Bounded {
+            buffer: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, sample: T) {
+        match self {
+            Self::Unbounded(samples) => samples.push(sample),
+            Self::Bounded { capacity: 0, .. } => {}
+            Self::Bounded { buffer, capacity } => {
+                if buffer.len() == *capacity {
+                    buffer.pop_front();
+                }
+                buffer.push_back(sample);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Unbounded(samples) => samples.len(),
+            Self::Bounded { buffer, .. } => buffer.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&mut self) {
+        match self {
+            Self::Unbounded(samples) => samples.clear(),
+            Self::Bounded { buffer, .. } => buffer.clear(),
+        }
+    }
+
+    /// Samples from oldest to newest.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+        match self {
+            Self::Unbounded(samples) => Box::new(samples.iter()),
+            Self::Bounded { buffer, .. } => Box::new(buffer.iter()),
+        }
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        match self {
+            Self::Unbounded(samples) => samples,
+            Self::Bounded { buffer, .. } => buffer.into(),
+        }
+    }
+}
+
+impl<T: Clone> Recording<T> {
+    pub fn to_vec(&self) -> Vec<T> {
+        self.iter().cloned().collect()
+    }
+}
+
+/// Records `(tick, neuron)` for every spike of the watched neurons.
+#[derive(Clone, Debug)]
+pub struct SpikeMonitor {
+    /// Watched neurons in ascending order, `None` watches all
+    neurons: Option<Vec<u32>>,
+    events: Recording<(u64, u32)>,
+}
+
+impl SpikeMonitor {
+    /// Watch every neuron of the model.
+    pub fn all(recording: Recording<(u64, u32)>) -> Self {
+        Self {
+            neurons: None,
+            events: recording,
+        }
+    }
+
+    /// Watch only the given neurons.
+    pub fn new(neurons: impl IntoIterator<Item = u32>, recording: Recording<(u64, u32)>) -> Self {
+        let mut neurons: Vec<u32> = neurons.into_iter().collect();
+        neurons.sort_unstable();
+        neurons.dedup();
+        Self {
+            neurons: Some(neurons),
+            events: recording,
+        }
+    }
+
+    /// Watch the neurons of a population.
+    pub fn population(population: &Population, recording: Recording<(u64, u32)>) -> Self {
+        Self::new(population.neurons(), recording)
+    }
+
+    /// Watched neurons in ascending order, `None` if it watches all.
+    pub fn neurons(&self) -> Option<&[u32]> {
+        self.neurons.as_deref()
+    }
+
+    pub fn events(&self) -> &Recording<(u64, u32)> {
+        &self.events
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// Recorded `(tick, neuron)` pairs in the order they happened.
+    pub fn to_vec(&self) -> Vec<(u64, u32)> {
+        self.events.to_vec()
+    }
+
+    pub fn into_vec(self) -> Vec<(u64, u32)> {
+        self.events.into_vec()
+    }
+
+    fn record(&mut self, time: u64, spiked: &[bool]) {
+        match &self.neurons {
+            None => {
+                for (i, _) in spiked.iter().enumerate().filter(|(_, s)| **s) {
+                    self.events.push((time, i as u32));
+                }
+            }
+            Some(neurons) => {
+                for &i in neurons {
+                    if spiked[i as usize] {
+                        self.events.push((time, i));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Samples the membrane potential of chosen neurons every `stride` ticks.
+#[derive(Clone, Debug)]
+pub struct StateMonitor {
+    neurons: Vec<u32>,
+    stride: u64,
+    /// (tick, potential of each watched neuron)
+    samples: Recording<(u64, Vec<f32>)>,
+}
+
+impl StateMonitor {
+    /// Panics if `stride` is 0.
+    pub fn new(
+        neurons: impl IntoIterator<Item = u32>,
+        stride: u64,
+        recording: Recording<(u64, Vec<f32>)>,
+    ) -> Self {
+        assert!(
+            stride >= 1,
+            "state monitor stride must be at least one tick"
+        );
+        Self {
+            neurons: neurons.into_iter().collect(),
+            stride,
+            samples: recording,
+        }
+    }
+
+    /// Sample the neurons of a population, in index order.
+    pub fn population(
+        population: &Population,
+        stride: u64,
+        recording: Recording<(u64, Vec<f32>)>,
+    ) -> Self {
+        Self::new(population.neurons(), stride, recording)
+    }
+
+    /// Watched neurons, in the order their values appear in each sample.
+    /// Neurons removed from the model since show up as
+    /// [`REMOVED`](crate::structure::REMOVED) and sample as NaN.
+    pub fn neurons(&self) -> &[u32] {
+        &self.neurons
+    }
+
+    pub fn samples(&self) -> &Recording<(u64, Vec<f32>)> {
+        &self.samples
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn to_vec(&self) -> Vec<(u64, Vec<f32>)> {
+        self.samples.to_vec()
+    }
+
+    pub fn into_vec(self) -> Vec<(u64, Vec<f32>)> {
+        self.samples.into_vec()
+    }
+
+    /// Recorded ticks and the trace of the `k`-th watched neuron.
+    pub fn trace(&self, k: usize) -> (Vec<u64>, Vec<f32>) {
+        self.samples.iter().map(|(t, vs)| (*t, vs[k])).unzip()
+    }
+
+    fn record(&mut self, time: u64, v: &[f32]) {
+        if !time.is_multiple_of(self.stride) {
+            return;
+        }
+        let values = self
+            .neurons
+            .iter()
+            .map(|&i| v.get(i as usize).copied().unwrap_or(f32::NAN))
+            .collect();
+        self.samples.push((time, values));
+    }
+}
+
+/// Handle of a spike monitor attached to a model.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SpikeMonitorId(usize);
+
+/// Handle of a state monitor attached to a model.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StateMonitorId(usize);
+
+/// Monitors attached to a model. Removed monitors leave an empty slot,
+/// so handles of the others stay valid.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Monitors {
+    pub(crate) spikes: Vec<Option<SpikeMonitor>>,
+    pub(crate) states: Vec<Option<StateMonitor>>,
+}
+
+impl Monitors {
+    pub(crate) fn add_spike(&mut self, monitor: SpikeMonitor) -> SpikeMonitorId {
+        self.spikes.push(Some(monitor));
+        SpikeMonitorId(self.spikes.len() - 1)
+    }
+
+    pub(crate) fn add_state(&mut self, monitor: StateMonitor) -> StateMonitorId {
+        self.states.push(Some(monitor));
+        StateMonitorId(self.states.len() - 1)
+    }
+
+    pub(crate) fn spike(&self, id: SpikeMonitorId) -> Option<&SpikeMonitor> {
+        self.spikes.get(id.0)?.as_ref()
+    }
+
+    pub(crate) fn spike_mut(&mut self, id: SpikeMonitorId) -> Option<&mut SpikeMonitor> {
+        self.spikes.get_mut(id.0)?.as_mut()
+    }
+
+    pub(crate) fn state(&self, id: StateMonitorId) -> Option<&StateMonitor> {
+        self.states.get(id.0)?.as_ref()
+    }
+
+    pub(crate) fn state_mut(&mut self, id: StateMonitorId) -> Option<&mut StateMonitor> {
+        self.states.get_mut(id.0)?.as_mut()
+    }
+
+    pub(crate) fn remove_spike(&mut self, id: SpikeMonitorId) -> Option<SpikeMonitor> {
+        self.spikes.get_mut(id.0)?.take()
+    }
+
+    pub(crate) fn remove_state(&mut self, id: StateMonitorId) -> Option<StateMonitor> {
+        self.states.get_mut(id.0)?.take()
+    }
+
+    /// Neurons whose potential is read when recording `time`.
+    pub(crate) fn sampled(&self, time: u64) -> impl Iterator<Item = u32> + '_ {
+        self.states
+            .iter()
+            .flatten()
+            .filter(move |m| time.is_multiple_of(m.stride))
+            .flat_map(|m| m.neurons.iter().copied())
+            .filter(|&i| i != REMOVED)
+    }
+
+    /// Follow a compaction of the model, `map` holds the new index of every
+    /// old neuron. Spike monitors stop watching removed neurons, state
+    /// monitors keep their slot.
+    pub(crate) fn remap(&mut self, map: &[u32]) {
+        let new = |i: u32| map.get(i as usize).copied().unwrap_or(REMOVED);
+        for monitor in self.spikes.iter_mut().flatten() {
+            if let Some(neurons) = &mut monitor.neurons {
+                *neurons = neurons
+                    .iter()
+                    .map(|&i| new(i))
+                    .filter(|&i| i != REMOVED)
+                    .collect();
+            }
+        }
+        for monitor in self.states.iter_mut().flatten() {
+            for i in &mut monitor.neurons {
+                *i = new(*i);
+            }
+        }
+    }
+
+    /// Like [`record`](Self::record), with the spikes of the tick given as
+    /// ascending neuron indices.
+    pub(crate) fn record_fired(&mut self, time: u64, fired: &[u32], v: &[f32]) {
+        for monitor in self.spikes.iter_mut().flatten() {
+            for &i in fired {
+                let watched = match &monitor.neurons {
+                    None => true,
+                    Some(neurons) => neurons.binary_search(&i).is_ok(),
+                };
+                if watched {
+                    monitor.events.push((time, i));
+                }
+            }
+        }
+        for monitor in self.states.iter_mut().flatten() {
+            monitor.record(time, v);
+        }
+    }
+
+    pub(crate) fn record(&mut self, time: u64, spiked: &[bool], v: &[f32]) {
+        for monitor in self.spikes.iter_mut().flatten() {
+            monitor.record(time, spiked);
+        }
+        for monitor in self.states.iter_mut().flatten() {
+            monitor.record(time, v);
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use serde::{Deserialize, Serialize};
+
+use super::{NeuronModel, NeuronPart, retain_mask, split_at_ends};
+
+/// Upper bound of the exponential term's argument, keeps the upswing finite
+const MAX_EXP_ARG: f32 = 20.0;
+
+/// Parameters of the adaptive exponential integrate-and-fire model
+/// (Brette & Gerstner 2005).
+///
+/// C v' = -g_l (v - e_l) + g_l delta_t exp((v - v_t) / delta_t) - w + I
+/// tau_w w' = a (v - e_l) - w
+/// if v >= v_peak: v = v_reset, w = w + b
+///
+/// Units are pF, nS, mV, ms and pA.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AdExParams {
+    /// Membrane capacitance
+    pub c: f32,
+    /// Leak conductance
+    pub g_l: f32,
+    /// Leak reversal potential
+    pub e_l: f32,
+    /// Threshold potential of the exponential term
+    pub v_t: f32,
+    /// Slope factor
+    pub delta_t: f32,
+    /// Subthreshold adaptation
+    pub a: f32,
+    /// Adaptation time constant
+    pub tau_w: f32,
+    /// Spike-triggered adaptation
+    pub b: f32,
+    /// Reset value after spike
+    pub v_reset: f32,
+    /// Potential at which a spike is detected
+    pub v_peak: f32,
+}
+
+impl Default for AdExParams {
+    fn default() -> Self {
+        // Values fitted to a regular spiking pyramidal cell in the original paper
+        Self {
+            c: 281.0,
+            g_l: 30.0,
+            e_l: -70.6,
+            v_t: -50.4,
+            delta_t: 2.0,
+            a: 4.0,
+            tau_w: 144.0,
+            b: 80.5,
+            v_reset: -70.6,
+            v_peak: 20.0,
+        }
+    }
+}
+
+/// Adaptive exponential integrate-and-fire neurons.
+/// Synaptic weights are added to v directly, in mV. External currents are in pA.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AdEx {
+    /// Membrane potential in mV
+    v: Vec<f32>,
+    /// Adaptation current in pA
+    w: Vec<f32>,
+    params: Vec<AdExParams>,
+    /// Milliseconds per tick
+    dt: f32,
+}
+
+impl Default for AdEx {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AdEx {
+    pub fn new() -> Self {
+        Self {
+            v: Vec::new(),
+            w: Vec::new(),
+            params: Vec::new(),
+            dt: 0.1,
+        }
+    }
+
+    /// `n` neurons sharing the same parameters.
+    pub fn uniform(n: usize, params: AdExParams) -> Self {
+        let mut neurons = Self::new();
+        for _ in 0..n {
+            neurons.push(params);
+        }
+        neurons
+    }
+
+    pub fn dt(&self) -> f32 {
+        self.dt
+    }
+
+    /// Set the simulated milliseconds per tick.
+    pub fn set_dt(&mut self, dt: f32) {
+        self.dt = dt;
+    }
+
+    pub fn params(&self, neuron: usize) -> &AdExParams {
+        &self.params[neuron]
+    }
+
+    pub fn set_params(&mut self, neuron: usize, params: AdExParams) {
+        self.params[neuron] = params;
+    }
+
+    /// Adaptation current of every neuron.
+    pub fn w(&self) -> &[f32] {
+        &self.w
+    }
+}
+
+/// Run of [`AdEx`] neurons, see [`NeuronModel::split`].
+pub struct AdExPart<'a> {
+    v: &'a mut [f32],
+    w: &'a mut [f32],
+    params: &'a [AdExParams],
+    dt: f32,
+}
+
+impl NeuronPart for AdExPart<'_> {
+    #[inline]
+    fn integrate(&mut self, i: usize, current: f32) {
+        let p = &self.params[i];
+        let (v, w) = (self.v[i], self.w[i]);
+        let exp_arg = ((v - p.v_t) / p.delta_t).min(MAX_EXP_ARG);
+        let dv = (-p.g_l * (v - p.e_l) + p.g_l * p.delta_t * exp_arg.exp() - w + current) / p.c;
+        let dw = (p.a * (v - p.e_l) - w) / p.tau_w;
+        self.v[i] = v + self.dt * dv;
+        self.w[i] = w + self.dt * dw;
+    }
+
+    #[inline]
+    fn spike(&self, i: usize) -> bool {
+        self.v[i] >= self.params[i].v_peak
+    }
+
+    #[inline]
+    fn reset(&mut self, i: usize) {
+        let p = &self.params[i];
+        self.v[i] = p.v_reset;
+        self.w[i] += p.b;
+    }
+
+    #[inline]
+    fn receive(&mut self, i: usize, weight: f32) {
+        self.v[i] += weight;
+    }
+}
+
+impl AdEx {
+    /// All neurons as one part, so single neurons update through the same code as split runs.
+    #[inline]
+    fn whole(&mut self) -> AdExPart<'_> {
+        AdExPart {
+            v: &mut self.v,
+            w: &mut self.w,
+            params: &self.params,
+            dt: self.dt,
+        }
+    }
+}
+
+impl NeuronModel for AdEx {
+    type Params = AdExParams;
+    type Part<'a> = AdExPart<'a>;
+
+    fn len(&self) -> usize {
+        self.v.len()
+    }
+
+    fn push(&mut self, params: AdExParams) {
+        self.v.push(params.e_l);
+        self.w.push(0.0);
+        self.params.push(params);
+    }
+
+    fn push_copy(&mut self, from: &Self, i: usize) {
+        self.push(from.neuron_params(i));
+        let last = self.v.len() - 1;
+        self.v[last] = from.v[i];
+        self.w[last] = from.w[i];
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.v.truncate(len);
+        self.w.truncate(len);
+        self.params.truncate(len);
+    }
+
+    fn retain(&mut self, keep: &[bool]) {
+        retain_mask(&mut self.v, keep);
+        retain_mask(&mut self.w, keep);
+        retain_mask(&mut self.params, keep);
+    }
+
+    fn neuron_params(&self, i: usize) -> AdExParams {
+        self.params[i]
+    }
+
+    fn check_params(&self, i: usize, problems: &mut Vec<&'static str>) {
+        if !self.dt.is_finite() || self.dt <= 0.0 {
+            problems.push("time step not positive");
+        }
+        let p = &self.params[i];
+        let all = [
+            p.c, p.g_l, p.e_l, p.v_t, p.delta_t, p.a, p.tau_w, p.b, p.v_reset, p.v_peak,
+        ];
+        if all.iter().any(|x| !x.is_finite()) {
+            problems.push("parameter is not finite");
+            return;
+        }
+        if p.c <= 0.0 || p.g_l <= 0.0 || p.delta_t <= 0.0 || p.tau_w <= 0.0 {
+            problems.push("capacitance, leak, slope and adaptation time must be positive");
+        }
+        if p.v_reset >= p.v_peak {
+            problems.push("v_reset not below v_peak");
+        }
+    }
+
+    fn array_lengths(&self) -> Vec<(&'static str, usize)> {
+        vec![("w", self.w.len()), ("params", self.params.len())]
+    }
+
+    fn v(&self) -> &[f32] {
+        &self.v
+    }
+
+    fn v_mut(&mut self) -> &mut [f32] {
+        &mut self.v
+    }
+
+    #[inline]
+    fn integrate(&mut self, i: usize, current: f32) {
+        self.whole().integrate(i, current);
+    }
+
+    #[inline]
+    fn spike(&self, i: usize) -> bool {
+        self.v[i] >= self.params[i].v_peak
+    }
+
+    #[inline]
+    fn reset(&mut self, i: usize) {
+        self.whole().reset(i);
+    }
+
+    fn split(&mut self, ends: &[usize]) -> Vec<AdExPart<'_>> {
+        let ws = split_at_ends(&mut self.w, ends);
+        let starts = std::iter::once(0).chain(ends.iter().copied());
+        split_at_ends(&mut self.v, ends)
+            .into_iter()
+            .zip(ws)
+            .zip(starts.zip(ends))
+            .map(|((v, w), (start, &end))| AdExPart {
+                v,
+                w,
+                params: &self.params[start..end],
+                dt: self.dt,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,325 @@
+use serde::{Deserialize, Serialize};
+
+use super::{NeuronModel, NeuronPart, retain_mask, split_at_ends};
+
+/// Membrane potential at which an Izhikevich neuron fires, in mV
+const V_PEAK: f32 = 30.0;
+/// Resting potential new neurons start at, in mV
+const V_REST: f32 = -65.0;
+
+/// Parameters of the Izhikevich (2003) simple model.
+///
+/// v' = 0.04v² + 5v + 140 - u + I
+/// u' = a(bv - u)
+/// if v >= 30 mV: v = c, u = u + d
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IzhikevichParams {
+    /// Time scale of the recovery variable u
+    pub a: f32,
+    /// Sensitivity of u to subthreshold fluctuations of v
+    pub b: f32,
+    /// After-spike reset value of v, in mV
+    pub c: f32,
+    /// After-spike increment of u
+    pub d: f32,
+}
+
+impl IzhikevichParams {
+    pub fn regular_spiking() -> Self {
+        Self {
+            a: 0.02,
+            b: 0.2,
+            c: -65.0,
+            d: 8.0,
+        }
+    }
+
+    pub fn intrinsically_bursting() -> Self {
+        Self {
+            a: 0.02,
+            b: 0.2,
+            c: -55.0,
+            d: 4.0,
+        }
+    }
+
+    pub fn chattering() -> Self {
+        Self {
+            a: 0.02,
+            b: 0.2,
+            c: -50.0,
+            d: 2.0,
+        }
+    }
+
+    pub fn fast_spiking() -> Self {
+        Self {
+            a: 0.1,
+            b: 0.2,
+            c: -65.0,
+            d: 2.0,
+        }
+    }
+
+    pub fn low_threshold_spiking() -> Self {
+        Self {
+            a: 0.02,
+            b: 0.25,
+            c: -65.0,
+            d: 2.0,
+        }
+    }
+}
+
+impl Default for IzhikevichParams {
+    fn default() -> Self {
+        Self::regular_spiking()
+    }
+}
+
+/// Izhikevich neurons. Synaptic weights are added to v directly, in mV.
+/// External currents enter the equation as I.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Izhikevich {
+    /// Membrane potential in mV
+    v: Vec<f32>,
+    /// Recovery variable
+    u: Vec<f32>,
+    a: Vec<f32>,
+    b: Vec<f32>,
+    c: Vec<f32>,
+    d: Vec<f32>,
+    /// Milliseconds per tick
+    dt: f32,
+}
+
+impl Default for Izhikevich {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Izhikevich {
+    pub fn new() -> Self {
+        Self {
+            v: Vec::new(),
+            u: Vec::new(),
+            a: Vec::new(),
+            b: Vec::new(),
+            c: Vec::new(),
+            d: Vec::new(),
+            dt: 1.0,
+        }
+    }
+
+    /// `n` neurons sharing the same parameters.
+    pub fn uniform(n: usize, params: IzhikevichParams) -> Self {
+        let mut neurons = Self::new();
+        for _ in 0..n {
+            neurons.push(params);
+        }
+        neurons
+    }
+
+    pub fn dt(&self) -> f32 {
+        self.dt
+    }
+
+    /// Set the simulated milliseconds per tick.
+    pub fn set_dt(&mut self, dt: f32) {
+        self.dt = dt;
+    }
+
+    pub fn params(&self, neuron: usize) -> IzhikevichParams {
+        IzhikevichParams {
+            a: self.a[neuron],
+            b: self.b[neuron],
+            c: self.c[neuron],
+            d: self.d[neuron],
+        }
+    }
+
+    pub fn set_params(&mut self, neuron: usize, params: IzhikevichParams) {
+        self.a[neuron] = params.a;
+        self.b[neuron] = params.b;
+        self.c[neuron] = params.c;
+        self.d[neuron] = params.d;
+    }
+
+    /// Recovery variable of every neuron.
+    pub fn u(&self) -> &[f32] {
+        &self.u
+    }
+}
+
+/// Run of [`Izhikevich`] neurons, see [`NeuronModel::split`].
+pub struct IzhikevichPart<'a> {
+    v: &'a mut [f32],
+    u: &'a mut [f32],
+    a: &'a [f32],
+    b: &'a [f32],
+    c: &'a [f32],
+    d: &'a [f32],
+    dt: f32,
+}
+
+impl NeuronPart for IzhikevichPart<'_> {
+    #[inline]
+    fn integrate(&mut self, i: usize, current: f32) {
+        let (mut v, u) = (self.v[i], self.u[i]);
+        // Two half steps for numerical stability, as in the original paper
+        let h = 0.5 * self.dt;
+        for _ in 0..2 {
+            v += h * (0.04 * v * v + 5.0 * v + 140.0 - u + current);
+        }
+        self.u[i] = u + self.dt * self.a[i] * (self.b[i] * v - u);
+        self.v[i] = v;
+    }
+
+    #[inline]
+    fn spike(&self, i: usize) -> bool {
+        self.v[i] >= V_PEAK
+    }
+
+    #[inline]
+    fn reset(&mut self, i: usize) {
+        self.v[i] = self.c[i];
+        self.u[i] += self.d[i];
+    }
+
+    #[inline]
+    fn receive(&mut self, i: usize, weight: f32) {
+        self.v[i] += weight;
+    }
+}
+
+impl Izhikevich {
+    /// All neurons as one part, so single neurons update through the same code as split runs.
+    #[inline]
+    fn whole(&mut self) -> IzhikevichPart<'_> {
+        IzhikevichPart {
+            v: &mut self.v,
+            u: &mut self.u,
+            a: &self.a,
+            b: &self.b,
+            c: &self.c,
+            d: &self.d,
+            dt: self.dt,
+        }
+    }
+}
+
+impl NeuronModel for Izhikevich {
+    type Params = IzhikevichParams;
+    type Part<'a> = IzhikevichPart<'a>;
+
+    fn len(&self) -> usize {
+        self.v.len()
+    }
+
+    fn push(&mut self, params: IzhikevichParams) {
+        self.v.push(V_REST);
+        self.u.push(params.b * V_REST);
+        self.a.push(params.a);
+        self.b.push(params.b);
+        self.c.push(params.c);
+        self.d.push(params.d);
+    }
+
+    fn push_copy(&mut self, from: &Self, i: usize) {
+        self.push(from.neuron_params(i));
+        let last = self.v.len() - 1;
+        self.v[last] = from.v[i];
+        self.u[last] = from.u[i];
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.v.truncate(len);
+        self.u.truncate(len);
+        self.a.truncate(len);
+        self.b.truncate(len);
+        self.c.truncate(len);
+        self.d.truncate(len);
+    }
+
+    fn retain(&mut self, keep: &[bool]) {
+        for values in [
+            &mut self.v,
+            &mut self.u,
+            &mut self.a,
+            &mut self.b,
+            &mut self.c,
+            &mut self.d,
+        ] {
+            retain_mask(values, keep);
+        }
+    }
+
+    fn neuron_params(&self, i: usize) -> IzhikevichParams {
+        self.params(i)
+    }
+
+    fn check_params(&self, i: usize, problems: &mut Vec<&'static str>) {
+        let params = [self.a[i], self.b[i], self.c[i], self.d[i]];
+        if params.iter().any(|p| !p.is_finite()) {
+            problems.push("parameter is not finite");
+        } else if self.c[i] >= V_PEAK {
+            problems.push("reset c not below the spike peak");
+        }
+        if !self.dt.is_finite() || self.dt <= 0.0 {
+            problems.push("time step not positive");
+        }
+    }
+
+    fn array_lengths(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            ("u", self.u.len()),
+            ("a", self.a.len()),
+            ("b", self.b.len()),
+            ("c", self.c.len()),
+            ("d", self.d.len()),
+        ]
+    }
+
+    fn v(&self) -> &[f32] {
+        &self.v
+    }
+
+    fn v_mut(&mut self) -> &mut [f32] {
+        &mut self.v
+    }
+
+    #[inline]
+    fn integrate(&mut self, i: usize, current: f32) {
+        self.whole().integrate(i, current);
+    }
+
+    #[inline]
+    fn spike(&self, i: usize) -> bool {
+        self.v[i] >= V_PEAK
+    }
+
+    #[inline]
+    fn reset(&mut self, i: usize) {
+        self.whole().reset(i);
+    }
+
+    fn split(&mut self, ends: &[usize]) -> Vec<IzhikevichPart<'_>> {
+        let us = split_at_ends(&mut self.u, ends);
+        let starts = std::iter::once(0).chain(ends.iter().copied());
+        split_at_ends(&mut self.v, ends)
+            .into_iter()
+            .zip(us)
+            .zip(starts.zip(ends))
+            .map(|((v, u), (start, &end))| IzhikevichPart {
+                v,
+                u,
+                a: &self.a[start..end],
+                b: &self.b[start..end],
+                c: &self.c[start..end],
+                d: &self.d[start..end],
+                dt: self.dt,
+            })
+            .collect()
+    }
+}
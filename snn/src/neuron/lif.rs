@@ -0,0 +1,352 @@
+use serde::{Deserialize, Serialize};
+
+use super::{NeuronModel, NeuronPart, retain_mask, split_at_ends};
+use crate::{DEFAULT_ALPHA, DEFAULT_REFRAC_LEN, PerNeuron};
+
+/// Per-neuron parameters of a leaky integrate-and-fire neuron.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LifParams {
+    /// Firing threshold
+    pub v_th: f32,
+    /// Reset value after spike
+    pub v_reset: f32,
+    /// Leak factor alpha = dt / tau, 0 < alpha <= 1
+    pub alpha: f32,
+    /// Refractory length in steps
+    pub refrac_len: u16,
+}
+
+impl Default for LifParams {
+    fn default() -> Self {
+        Self {
+            v_th: 1.0,
+            v_reset: 0.0,
+            alpha: DEFAULT_ALPHA,
+            refrac_len: DEFAULT_REFRAC_LEN,
+        }
+    }
+}
+
+/// Leaky integrate-and-fire neurons.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Lif {
+    /// Membrane potential v_t
+    v: Vec<f32>,
+    /// Leak factor alpha = dt / tau, 0 < alpha <= 1
+    alpha: PerNeuron<f32>,
+    /// Firing threshold
+    v_th: Vec<f32>,
+    /// Reset value after spike
+    v_reset: Vec<f32>,
+    /// Remaining refractory steps; 0 means active
+    refrac: Vec<u16>,
+    /// Refractory length in steps
+    refrac_len: PerNeuron<u16>,
+}
+
+impl Default for Lif {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lif {
+    pub fn new() -> Self {
+        // alpha: small leak per step
+        // refrac_len: short refractory period
+        Self {
+            v: Vec::new(),
+            alpha: PerNeuron::Uniform(DEFAULT_ALPHA),
+            v_th: Vec::new(),
+            v_reset: Vec::new(),
+            refrac: Vec::new(),
+            refrac_len: PerNeuron::Uniform(DEFAULT_REFRAC_LEN),
+        }
+    }
+
+    /// Empty population with room for `neurons` neurons.
+    pub fn with_capacity(neurons: usize) -> Self {
+        Self {
+            v: Vec::with_capacity(neurons),
+            alpha: PerNeuron::Uniform(DEFAULT_ALPHA),
+            v_th: Vec::with_capacity(neurons),
+            v_reset: Vec::with_capacity(neurons),
+            refrac: Vec::with_capacity(neurons),
+            refrac_len: PerNeuron::Uniform(DEFAULT_REFRAC_LEN),
+        }
+    }
+
+    /// `n` neurons sharing the same parameters.
+    pub fn uniform(n: usize, params: LifParams) -> Self {
+        Self {
+            v: vec![0.0; n],
+            alpha: PerNeuron::Uniform(params.alpha),
+            v_th: vec![params.v_th; n],
+            v_reset: vec![params.v_reset; n],
+            refrac: vec![0; n],
+            refrac_len: PerNeuron::Uniform(params.refrac_len),
+        }
+    }
+
+    pub fn alpha(&self, neuron: usize) -> f32 {
+        self.alpha.get(neuron)
+    }
+
+    pub fn set_alpha(&mut self, neuron: usize, alpha: f32) {
+        let n = self.v.len();
+        self.alpha.set(neuron, alpha, n);
+    }
+
+    /// Use the same leak factor for all neurons.
+    pub fn set_alpha_all(&mut self, alpha: f32) {
+        self.alpha.fill(alpha);
+    }
+
+    pub fn refrac_len(&self, neuron: usize) -> u16 {
+        self.refrac_len.get(neuron)
+    }
+
+    pub fn set_refrac_len(&mut self, neuron: usize, refrac_len: u16) {
+        let n = self.v.len();
+        self.refrac_len.set(neuron, refrac_len, n);
+    }
+
+    /// Use the same refractory length for all neurons.
+    pub fn set_refrac_len_all(&mut self, refrac_len: u16) {
+        self.refrac_len.fill(refrac_len);
+    }
+
+    pub fn v_th(&self, neuron: usize) -> f32 {
+        self.v_th[neuron]
+    }
+
+    pub fn set_v_th(&mut self, neuron: usize, v_th: f32) {
+        self.v_th[neuron] = v_th;
+    }
+
+    pub fn set_v_th_all(&mut self, v_th: f32) {
+        self.v_th.fill(v_th);
+    }
+
+    pub fn v_reset(&self, neuron: usize) -> f32 {
+        self.v_reset[neuron]
+    }
+
+    pub fn set_v_reset(&mut self, neuron: usize, v_reset: f32) {
+        self.v_reset[neuron] = v_reset;
+    }
+
+    pub fn set_v_reset_all(&mut self, v_reset: f32) {
+        self.v_reset.fill(v_reset);
+    }
+
+    /// Remaining refractory steps of every neuron.
+    pub fn refrac(&self) -> &[u16] {
+        &self.refrac
+    }
+
+    /// All neurons as one part, so single neurons update through the same code as split runs.
+    #[inline]
+    fn whole(&mut self) -> LifPart<'_> {
+        LifPart {
+            offset: 0,
+            v: &mut self.v,
+            refrac: &mut self.refrac,
+            alpha: &self.alpha,
+            v_th: &self.v_th,
+            v_reset: &self.v_reset,
+            refrac_len: &self.refrac_len,
+        }
+    }
+
+    /// Whether neuron `i` can only fire after input: its threshold is positive
+    /// and the leak never pushes the potential away from 0.
+    pub(crate) fn quiescent(&self, i: usize) -> bool {
+        self.v_th[i] > 0.0 && (0.0..=1.0).contains(&self.alpha.get(i))
+    }
+
+    /// First tick after `now` on which neuron `i` could fire without input,
+    /// `None` if it never does. Only meaningful for quiescent neurons.
+    pub(crate) fn next_spontaneous(&self, i: usize, now: u64) -> Option<u64> {
+        // The leak only brings the potential closer to 0, so a neuron below
+        // threshold stays there, and one above it fires once it is not refractory
+        (self.v[i] >= self.v_th[i]).then(|| now + self.refrac[i].max(1) as u64)
+    }
+
+    /// Advance neuron `i` by `ticks` ticks without input, exactly as that many
+    /// calls to `integrate(i, 0.0)` would.
+    pub(crate) fn idle(&mut self, i: usize, ticks: u64) {
+        let keep = 1.0 - self.alpha.get(i);
+        for _ in 0..ticks {
+            // Same operations as `integrate`, so the result matches to the bit
+            let v = self.v[i] * keep + 0.0;
+            let settled = v.to_bits() == self.v[i].to_bits();
+            self.v[i] = v;
+            if settled {
+                break;
+            }
+        }
+        let ticks = ticks.min(u16::MAX as u64) as u16;
+        self.refrac[i] = self.refrac[i].saturating_sub(ticks);
+    }
+}
+
+/// Run of [`Lif`] neurons, see [`NeuronModel::split`].
+pub struct LifPart<'a> {
+    /// Index of the first neuron of the run in the population
+    offset: usize,
+    v: &'a mut [f32],
+    refrac: &'a mut [u16],
+    alpha: &'a PerNeuron<f32>,
+    v_th: &'a [f32],
+    v_reset: &'a [f32],
+    refrac_len: &'a PerNeuron<u16>,
+}
+
+impl NeuronPart for LifPart<'_> {
+    #[inline]
+    fn integrate(&mut self, i: usize, current: f32) {
+        // Leak membrane potential, the current adds to v directly
+        self.v[i] = self.v[i] * (1.0 - self.alpha.get(self.offset + i)) + current;
+        self.refrac[i] = self.refrac[i].saturating_sub(1);
+    }
+
+    #[inline]
+    fn spike(&self, i: usize) -> bool {
+        self.refrac[i] == 0 && self.v[i] >= self.v_th[self.offset + i]
+    }
+
+    #[inline]
+    fn reset(&mut self, i: usize) {
+        self.v[i] = self.v_reset[self.offset + i];
+        // The reset tick already counts as the first refractory step
+        self.refrac[i] = self.refrac_len.get(self.offset + i).saturating_sub(1);
+    }
+
+    #[inline]
+    fn receive(&mut self, i: usize, weight: f32) {
+        self.v[i] += weight;
+    }
+}
+
+impl NeuronModel for Lif {
+    type Params = LifParams;
+    type Part<'a> = LifPart<'a>;
+
+    fn len(&self) -> usize {
+        self.v.len()
+    }
+
+    fn push(&mut self, params: LifParams) {
+        let n = self.v.len();
+        self.alpha.push(params.alpha, n);
+        self.refrac_len.push(params.refrac_len, n);
+        self.v.push(0.0);
+        self.v_th.push(params.v_th);
+        self.v_reset.push(params.v_reset);
+        self.refrac.push(0);
+    }
+
+    fn push_copy(&mut self, from: &Self, i: usize) {
+        self.push(from.neuron_params(i));
+        let last = self.v.len() - 1;
+        self.v[last] = from.v[i];
+        self.refrac[last] = from.refrac[i];
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.v.truncate(len);
+        self.alpha.truncate(len);
+        self.v_th.truncate(len);
+        self.v_reset.truncate(len);
+        self.refrac.truncate(len);
+        self.refrac_len.truncate(len);
+    }
+
+    fn retain(&mut self, keep: &[bool]) {
+        retain_mask(&mut self.v, keep);
+        self.alpha.retain(keep);
+        retain_mask(&mut self.v_th, keep);
+        retain_mask(&mut self.v_reset, keep);
+        retain_mask(&mut self.refrac, keep);
+        self.refrac_len.retain(keep);
+    }
+
+    fn neuron_params(&self, i: usize) -> LifParams {
+        LifParams {
+            v_th: self.v_th[i],
+            v_reset: self.v_reset[i],
+            alpha: self.alpha.get(i),
+            refrac_len: self.refrac_len.get(i),
+        }
+    }
+
+    fn check_params(&self, i: usize, problems: &mut Vec<&'static str>) {
+        let alpha = self.alpha.get(i);
+        if alpha.is_nan() || alpha <= 0.0 || alpha > 1.0 {
+            problems.push("alpha outside (0, 1]");
+        }
+        if !self.v_th[i].is_finite() || !self.v_reset[i].is_finite() {
+            problems.push("threshold or reset is not finite");
+        } else if self.v_reset[i] >= self.v_th[i] {
+            problems.push("v_reset not below v_th");
+        }
+    }
+
+    fn array_lengths(&self) -> Vec<(&'static str, usize)> {
+        let mut lengths = vec![
+            ("v_th", self.v_th.len()),
+            ("v_reset", self.v_reset.len()),
+            ("refrac", self.refrac.len()),
+        ];
+        if let PerNeuron::Each(alpha) = &self.alpha {
+            lengths.push(("alpha", alpha.len()));
+        }
+        if let PerNeuron::Each(refrac_len) = &self.refrac_len {
+            lengths.push(("refrac_len", refrac_len.len()));
+        }
+        lengths
+    }
+
+    fn v(&self) -> &[f32] {
+        &self.v
+    }
+
+    fn v_mut(&mut self) -> &mut [f32] {
+        &mut self.v
+    }
+
+    #[inline]
+    fn integrate(&mut self, i: usize, current: f32) {
+        self.whole().integrate(i, current);
+    }
+
+    #[inline]
+    fn spike(&self, i: usize) -> bool {
+        self.refrac[i] == 0 && self.v[i] >= self.v_th[i]
+    }
+
+    #[inline]
+    fn reset(&mut self, i: usize) {
+        self.whole().reset(i);
+    }
+
+    fn split(&mut self, ends: &[usize]) -> Vec<LifPart<'_>> {
+        let refracs = split_at_ends(&mut self.refrac, ends);
+        let starts = std::iter::once(0).chain(ends.iter().copied());
+        split_at_ends(&mut self.v, ends)
+            .into_iter()
+            .zip(refracs)
+            .zip(starts)
+            .map(|((v, refrac), offset)| LifPart {
+                offset,
+                v,
+                refrac,
+                alpha: &self.alpha,
+                v_th: &self.v_th,
+                v_reset: &self.v_reset,
+                refrac_len: &self.refrac_len,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,120 @@
+//! Neuron dynamics that can drive a [`Model`](crate::Model).
+//!
+//! Each implementation stores the state and parameters of a whole population.
+//! The model calls into it once per neuron and tick, synapse delivery is
+//! shared by all of them.
+
+mod adex;
+mod izhikevich;
+mod lif;
+
+pub use adex::{AdEx, AdExParams, AdExPart};
+pub use izhikevich::{Izhikevich, IzhikevichParams, IzhikevichPart};
+pub use lif::{Lif, LifParams, LifPart};
+
+pub trait NeuronModel {
+    /// Parameters of a single neuron
+    type Params: Clone;
+
+    /// Borrowed run of consecutive neurons, see [`split`](Self::split)
+    type Part<'a>: NeuronPart
+    where
+        Self: 'a;
+
+    /// Number of neurons in the population
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Append a neuron in its resting state
+    fn push(&mut self, params: Self::Params);
+
+    /// Parameters of neuron `i`, pushing them gives a copy of it at rest
+    fn neuron_params(&self, i: usize) -> Self::Params;
+
+    /// Append a copy of neuron `i` of `from` in its current state. The
+    /// default only carries the potential over, models with more state
+    /// override it.
+    fn push_copy(&mut self, from: &Self, i: usize) {
+        self.push(from.neuron_params(i));
+        let last = self.len() - 1;
+        self.v_mut()[last] = from.v()[i];
+    }
+
+    /// Add a description of every implausible parameter of neuron `i` to
+    /// `problems`, as reported by [`Model::validate`](crate::Model::validate).
+    ///
+    /// Only called for neurons that every array of
+    /// [`array_lengths`](Self::array_lengths) covers.
+    fn check_params(&self, i: usize, problems: &mut Vec<&'static str>);
+
+    /// Name and length of every per-neuron array besides `v`, each of which
+    /// should hold [`len`](Self::len) entries.
+    fn array_lengths(&self) -> Vec<(&'static str, usize)> {
+        Vec::new()
+    }
+
+    /// Keep the first `len` neurons. Population-wide settings such as the
+    /// time step stay.
+    fn truncate(&mut self, len: usize);
+
+    /// Keep the neurons whose entry in `keep` is true, in order.
+    fn retain(&mut self, keep: &[bool]);
+
+    /// Membrane potential of every neuron
+    fn v(&self) -> &[f32];
+
+    fn v_mut(&mut self) -> &mut [f32];
+
+    /// Advance neuron `i` by one tick under the external `current`.
+    /// Called for every neuron that did not spike on the previous tick.
+    fn integrate(&mut self, i: usize, current: f32);
+
+    /// Whether neuron `i` fires in its current state.
+    fn spike(&self, i: usize) -> bool;
+
+    /// Called instead of `integrate` on the tick after neuron `i` spiked.
+    fn reset(&mut self, i: usize);
+
+    /// Synaptic input of `weight` arriving at neuron `i`.
+    fn receive(&mut self, i: usize, weight: f32) {
+        self.v_mut()[i] += weight;
+    }
+
+    /// Split the population into runs of neurons ending before each of
+    /// `ends`, which ascend up to `len()`, so they can be updated on
+    /// different threads. Parts update exactly like the population.
+    fn split(&mut self, ends: &[usize]) -> Vec<Self::Part<'_>>;
+}
+
+/// Consecutive neurons of a population, indexed from the first of the run.
+pub trait NeuronPart: Send {
+    fn integrate(&mut self, i: usize, current: f32);
+
+    fn spike(&self, i: usize) -> bool;
+
+    fn reset(&mut self, i: usize);
+
+    fn receive(&mut self, i: usize, weight: f32);
+}
+
+/// Keep the values whose entry in `keep` is true, one entry per value.
+pub(crate) fn retain_mask<T>(values: &mut Vec<T>, keep: &[bool]) {
+    let mut keep = keep.iter();
+    values.retain(|_| keep.next().copied().unwrap_or(false));
+}
+
+/// Split `slice` into consecutive runs ending before each of `ends`.
+pub(crate) fn split_at_ends<'a, T>(mut slice: &'a mut [T], ends: &[usize]) -> Vec<&'a mut [T]> {
+    let mut runs = Vec::with_capacity(ends.len());
+    let mut start = 0;
+    for &end in ends {
+        let (run, rest) = std::mem::take(&mut slice).split_at_mut(end - start);
+        runs.push(run);
+        slice = rest;
+        start = end;
+    }
+    runs
+}
@@ -0,0 +1,256 @@
+//! Multithreaded ticks.
+//!
+//! With more than one thread, [`Model::tick`] splits the neurons into one run
+//! of consecutive indices per thread. A thread owns the neurons of its run and
+//! their outgoing synapses: it resets or integrates them, applies the spikes
+//! arriving at them in delivery order, and detects their spikes. The due
+//! deliveries are sorted into one list per run beforehand, keeping their
+//! order. Nothing is written by two threads, and the spikes of all runs are
+//! queued in neuron order afterwards, so results are bit-identical to the
+//! serial tick for any thread count. STDP and monitors stay serial.
+
+use std::ops::Range;
+
+use rayon::prelude::*;
+
+use crate::neuron::split_at_ends;
+use crate::{Error, Model, NeuronModel, NeuronPart};
+
+/// Worker threads of a model.
+pub(crate) struct Threads {
+    pool: rayon::ThreadPool,
+    /// Synapses delivering to each run on the current tick, in slot order
+    due: Vec<Vec<u32>>,
+    /// Synapses armed by each run on the current tick, as (ring slot, synapse)
+    armed: Vec<Vec<(usize, u32)>>,
+}
+
+impl Threads {
+    fn new(threads: usize) -> Result<Self, Error> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()?;
+        let runs = pool.current_num_threads();
+        Ok(Self {
+            due: vec![Vec::new(); runs],
+            armed: vec![Vec::new(); runs],
+            pool,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.pool.current_num_threads()
+    }
+}
+
+/// What every run reads on a tick.
+struct Shared<'a> {
+    time: u64,
+    ring_len: u64,
+    bias: &'a [f32],
+    out_offset: &'a [u32],
+    receiver: &'a [u32],
+    weight: &'a [f32],
+    delay: &'a [u16],
+}
+
+/// One thread's share of a tick.
+struct Run<'a, P> {
+    neurons: Range<usize>,
+    part: P,
+    /// Synapses delivering to the run's neurons on this tick
+    due: &'a [u32],
+    spiked: &'a mut [bool],
+    input: &'a mut [f32],
+    /// Outgoing synapses of the run's neurons
+    synapses: Range<usize>,
+    state: &'a mut [u32],
+    armed: &'a mut Vec<(usize, u32)>,
+}
+
+impl<P: NeuronPart> Run<'_, P> {
+    fn step(mut self, shared: &Shared) {
+        let start = self.neurons.start;
+
+        // 1) Reset spiked neurons, integrate the rest under external currents
+        for i in 0..self.neurons.len() {
+            if self.spiked[i] {
+                self.part.reset(i);
+                self.spiked[i] = false;
+            } else {
+                let current = self.input[i] + shared.bias[start + i];
+                self.part.integrate(i, current);
+            }
+        }
+        self.input.fill(0.0);
+
+        // 2) Deliver to this run's neurons, in the order of the whole slot
+        for &j in self.due {
+            let j = j as usize;
+            let recv = shared.receiver[j] as usize;
+            self.part.receive(recv - start, shared.weight[j]);
+        }
+
+        // 3) Detect spikes and arm outgoing synapses
+        self.armed.clear();
+        for i in 0..self.neurons.len() {
+            if !self.part.spike(i) {
+                continue;
+            }
+            self.spiked[i] = true;
+            let first = shared.out_offset[start + i] as usize;
+            let last = shared.out_offset[start + i + 1] as usize;
+            for j in first..last {
+                let arrival = (shared.time + shared.delay[j] as u64) % shared.ring_len;
+                self.armed.push((arrival as usize, j as u32));
+                self.state[j - self.synapses.start] += 1;
+            }
+        }
+    }
+}
+
+impl<N: NeuronModel> Model<N> {
+    /// Run ticks on `threads` threads, 0 for one per core and 1 for the
+    /// serial tick. Results are the same for every thread count.
+    ///
+    /// Fails with [`Error::Threads`] if the threads cannot be started, the
+    /// model keeps its previous threads then.
+    pub fn set_threads(&mut self, threads: usize) -> Result<(), Error> {
+        self.threads = match threads {
+            1 => None,
+            _ => Some(Threads::new(threads)?),
+        };
+        Ok(())
+    }
+
+    /// Number of threads ticks run on.
+    pub fn threads(&self) -> usize {
+        self.threads.as_ref().map_or(1, Threads::len)
+    }
+
+    /// Phases 1 to 3 of a tick on the worker threads. Returns false without
+    /// doing anything if the model ticks serially.
+    pub(crate) fn step_parallel(&mut self) -> bool {
+        let Some(threads) = &mut self.threads else {
+            return false;
+        };
+        let n = self.state.neurons.len();
+        let runs = threads.len();
+        let ends: Vec<usize> = (1..=runs).map(|k| n * k / runs).collect();
+        let synapse_ends: Vec<usize> = ends
+            .iter()
+            .map(|&e| self.topology.out_offset[e] as usize)
+            .collect();
+
+        // Sort the due deliveries by the run of their receiver, in one pass
+        let ring_len = self.state.pending.len() as u64;
+        let slot = (self.state.time % ring_len) as usize;
+        let mut due = std::mem::take(&mut self.state.pending[slot]);
+        for bucket in &mut threads.due {
+            bucket.clear();
+        }
+        for &j in &due {
+            let recv = self.topology.receiver[j as usize] as usize;
+            threads.due[ends.partition_point(|&end| end <= recv)].push(j);
+            self.state.in_flight[j as usize] -= 1;
+        }
+        let shared = Shared {
+            time: self.state.time,
+            ring_len,
+            bias: &self.bias,
+            out_offset: &self.topology.out_offset,
+            receiver: &self.topology.receiver,
+            weight: &self.topology.weight,
+            delay: &self.topology.delay,
+        };
+
+        let run = |ends: &[usize], k: usize| if k == 0 { 0 } else { ends[k - 1] }..ends[k];
+        let work: Vec<_> = self
+            .state
+            .neurons
+            .split(&ends)
+            .into_iter()
+            .zip(split_at_ends(&mut self.state.spiked, &ends))
+            .zip(split_at_ends(&mut self.state.input, &ends))
+            .zip(split_at_ends(&mut self.state.in_flight, &synapse_ends))
+            .zip(threads.due.iter().zip(threads.armed.iter_mut()))
+            .enumerate()
+            .map(
+                |(k, ((((part, spiked), input), state), (due, armed)))| Run {
+                    neurons: run(&ends, k),
+                    part,
+                    due,
+                    spiked,
+                    input,
+                    synapses: run(&synapse_ends, k),
+                    state,
+                    armed,
+                },
+            )
+            .collect();
+        threads
+            .pool
+            .install(|| work.into_par_iter().for_each(|run| run.step(&shared)));
+
+        // Keep the allocation of the slot around for later ticks
+        due.clear();
+        self.state.pending[slot] = due;
+        for armed in &threads.armed {
+            for &(arrival, j) in armed {
+                self.state.pending[arrival].push(j);
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Model;
+    use crate::topology::tests::random_network;
+
+    /// Ticks on any number of threads are bit-identical to the serial tick.
+    #[test]
+    fn matches_serial() {
+        let build = || {
+            let mut model = random_network(101, 0.08, -0.3..0.8, 5, 7);
+            for i in (0..101).step_by(9) {
+                model.set_bias(i, 0.12);
+            }
+            model
+        };
+        let mut serial = build();
+        let mut parallel: Vec<Model> = [2, 3, 4]
+            .into_iter()
+            .map(|threads| {
+                let mut model = build();
+                model.set_threads(threads).unwrap();
+                model
+            })
+            .collect();
+
+        let mut spikes = 0;
+        for t in 0..300 {
+            if t % 20 == 0 {
+                for model in parallel.iter_mut().chain([&mut serial]) {
+                    model.add_input(t % 101, 2.0);
+                }
+            }
+            serial.tick();
+            spikes += serial.spiked().iter().filter(|&&s| s).count();
+            for model in &mut parallel {
+                model.tick();
+                assert_eq!(serial.spiked(), model.spiked());
+                assert_eq!(serial.synapse_states(), model.synapse_states());
+                let bits = |m: &Model| {
+                    m.neuron_vs()
+                        .iter()
+                        .map(|v| v.to_bits())
+                        .collect::<Vec<_>>()
+                };
+                assert_eq!(bits(&serial), bits(model));
+            }
+        }
+        assert!(spikes > 100);
+    }
+}
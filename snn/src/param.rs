@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+use crate::neuron::retain_mask;
+
+/// A neuron parameter that is either shared by all neurons or stored per neuron.
+///
+/// The uniform case needs no per-neuron storage; setting a single neuron to a
+/// different value expands it into one value per neuron.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PerNeuron<T> {
+    Uniform(T),
+    Each(Vec<T>),
+}
+
+impl<T: Copy + PartialEq> PerNeuron<T> {
+    /// Collapses to `Uniform` if all values are equal.
+    /// An empty iterator yields `Uniform(fallback)`.
+    pub fn from_values(values: impl IntoIterator<Item = T>, fallback: T) -> Self {
+        let values: Vec<T> = values.into_iter().collect();
+        match values.first() {
+            None => Self::Uniform(fallback),
+            Some(&first) if values.iter().all(|&v| v == first) => Self::Uniform(first),
+            Some(_) => Self::Each(values),
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, i: usize) -> T {
+        match self {
+            Self::Uniform(v) => *v,
+            Self::Each(vs) => vs[i],
+        }
+    }
+
+    /// Sets the value of neuron `i` out of `len` neurons.
+    pub fn set(&mut self, i: usize, value: T, len: usize) {
+        match self {
+            Self::Uniform(v) if *v == value => {}
+            Self::Uniform(v) => {
+                let mut vs = vec![*v; len];
+                vs[i] = value;
+                *self = Self::Each(vs);
+            }
+            Self::Each(vs) => vs[i] = value,
+        }
+    }
+
+    /// Appends the value of a new neuron, `len` is the neuron count before pushing.
+    pub fn push(&mut self, value: T, len: usize) {
+        match self {
+            Self::Uniform(v) if len == 0 => *v = value,
+            Self::Uniform(v) if *v == value => {}
+            Self::Uniform(v) => {
+                let mut vs = vec![*v; len];
+                vs.push(value);
+                *self = Self::Each(vs);
+            }
+            Self::Each(vs) => vs.push(value),
+        }
+    }
+
+    /// Keeps the values of the first `len` neurons.
+    pub fn truncate(&mut self, len: usize) {
+        if let Self::Each(vs) = self {
+            vs.truncate(len);
+        }
+    }
+
+    /// Keeps the values of the neurons whose entry in `keep` is true.
+    pub fn retain(&mut self, keep: &[bool]) {
+        if let Self::Each(vs) = self {
+            retain_mask(vs, keep);
+        }
+    }
+
+    /// Sets all neurons to `value`, dropping per-neuron storage.
+    pub fn fill(&mut self, value: T) {
+        *self = Self::Uniform(value);
+    }
+
+    pub fn is_uniform(&self) -> bool {
+        matches!(self, Self::Uniform(_))
+    }
+}
@@ -0,0 +1,613 @@
+//! Versioned save files for [`Model`].
+//!
+//! Two encodings carry the same content: RON for humans, and a compact binary
+//! form of a magic tag, a little-endian `u32` version and a bincode payload.
+//! Attached monitors are not saved. A model with structural edits waiting for
+//! [`compact`](Model::compact) is refused, compact it first.
+//!
+//! Loading runs [`Model::validate`] on the decoded model, so a file that
+//! parses but breaks an invariant fails with [`PersistError::Invalid`].
+
+use std::{fs, path::Path, sync::Arc};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{
+    Model, NeuronKind, NeuronModel, Population, ReverseIndex, SimState, Stimulus, Topology,
+    ValidationReport, monitor::Monitors, plasticity::Plasticity, structure::Structure,
+};
+
+/// Tag at the start of binary files
+const MAGIC: &[u8; 4] = b"SNNM";
+/// Value of the `format` field in RON files
+const FORMAT_NAME: &str = "snn-model";
+/// Bump when the layout of `ModelData` changes, and keep a loader for the old one.
+const FORMAT_VERSION: u32 = 6;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PersistError {
+    #[error("i/o error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("not a model file")]
+    NotAModel,
+    #[error("unsupported model file version {0}")]
+    UnsupportedVersion(u32),
+    #[error("RON error: {0}")]
+    Ron(#[from] ron::Error),
+    #[error("RON parse error: {0}")]
+    RonParse(#[from] ron::error::SpannedError),
+    #[error("binary encode error: {0}")]
+    Encode(#[from] bincode::error::EncodeError),
+    #[error("binary decode error: {0}")]
+    Decode(#[from] bincode::error::DecodeError),
+    #[error("malformed model: {0}")]
+    Malformed(&'static str),
+    #[error("invalid model: {0}")]
+    Invalid(ValidationReport),
+    #[error("structural edits are waiting for compaction")]
+    PendingEdits,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Ron,
+    Binary,
+}
+
+/// Borrowed view of everything that gets saved
+#[derive(Serialize)]
+struct ModelDataRef<'a, N> {
+    neurons: &'a N,
+    spiked: &'a [bool],
+    input: &'a [f32],
+    bias: &'a [f32],
+    kinds: &'a [NeuronKind],
+    dale: bool,
+    out_offset: &'a [u32],
+    receiver: &'a [u32],
+    weight: &'a [f32],
+    delay: &'a [u16],
+    state: &'a [u32],
+    pending: &'a [Vec<u32>],
+    time: u64,
+    plasticity: Option<&'a Plasticity>,
+    stimuli: &'a [Stimulus],
+    coordinates: Option<&'a [[f32; 3]]>,
+    populations: &'a [Population],
+    structure: Option<&'a Structure>,
+}
+
+/// Owned counterpart of `ModelDataRef`, current format version
+#[derive(Deserialize)]
+struct ModelData<N> {
+    neurons: N,
+    spiked: Vec<bool>,
+    input: Vec<f32>,
+    bias: Vec<f32>,
+    kinds: Vec<NeuronKind>,
+    dale: bool,
+    out_offset: Vec<u32>,
+    receiver: Vec<u32>,
+    weight: Vec<f32>,
+    delay: Vec<u16>,
+    state: Vec<u32>,
+    pending: Vec<Vec<u32>>,
+    time: u64,
+    plasticity: Option<Plasticity>,
+    stimuli: Vec<Stimulus>,
+    coordinates: Option<Vec<[f32; 3]>>,
+    populations: Vec<Population>,
+    structure: Option<Structure>,
+}
+
+/// Format version 5, before stable keys
+#[derive(Deserialize)]
+struct ModelDataV5<N> {
+    neurons: N,
+    spiked: Vec<bool>,
+    input: Vec<f32>,
+    bias: Vec<f32>,
+    kinds: Vec<NeuronKind>,
+    dale: bool,
+    out_offset: Vec<u32>,
+    receiver: Vec<u32>,
+    weight: Vec<f32>,
+    delay: Vec<u16>,
+    state: Vec<u32>,
+    pending: Vec<Vec<u32>>,
+    time: u64,
+    plasticity: Option<Plasticity>,
+    stimuli: Vec<Stimulus>,
+    coordinates: Option<Vec<[f32; 3]>>,
+    populations: Vec<Population>,
+}
+
+impl<N> From<ModelDataV5<N>> for ModelData<N> {
+    fn from(v5: ModelDataV5<N>) -> Self {
+        Self {
+            neurons: v5.neurons,
+            spiked: v5.spiked,
+            input: v5.input,
+            bias: v5.bias,
+            kinds: v5.kinds,
+            dale: v5.dale,
+            out_offset: v5.out_offset,
+            receiver: v5.receiver,
+            weight: v5.weight,
+            delay: v5.delay,
+            state: v5.state,
+            pending: v5.pending,
+            time: v5.time,
+            plasticity: v5.plasticity,
+            stimuli: v5.stimuli,
+            coordinates: v5.coordinates,
+            populations: v5.populations,
+            structure: None,
+        }
+    }
+}
+
+/// Format version 4, before populations
+#[derive(Deserialize)]
+struct ModelDataV4<N> {
+    neurons: N,
+    spiked: Vec<bool>,
+    input: Vec<f32>,
+    bias: Vec<f32>,
+    kinds: Vec<NeuronKind>,
+    dale: bool,
+    out_offset: Vec<u32>,
+    receiver: Vec<u32>,
+    weight: Vec<f32>,
+    delay: Vec<u16>,
+    state: Vec<u32>,
+    pending: Vec<Vec<u32>>,
+    time: u64,
+    plasticity: Option<Plasticity>,
+    stimuli: Vec<Stimulus>,
+    coordinates: Option<Vec<[f32; 3]>>,
+}
+
+impl<N> From<ModelDataV4<N>> for ModelDataV5<N> {
+    fn from(v4: ModelDataV4<N>) -> Self {
+        Self {
+            neurons: v4.neurons,
+            spiked: v4.spiked,
+            input: v4.input,
+            bias: v4.bias,
+            kinds: v4.kinds,
+            dale: v4.dale,
+            out_offset: v4.out_offset,
+            receiver: v4.receiver,
+            weight: v4.weight,
+            delay: v4.delay,
+            state: v4.state,
+            pending: v4.pending,
+            time: v4.time,
+            plasticity: v4.plasticity,
+            stimuli: v4.stimuli,
+            coordinates: v4.coordinates,
+            populations: Vec::new(),
+        }
+    }
+}
+
+/// Format version 3, before neuron kinds
+#[derive(Deserialize)]
+struct ModelDataV3<N> {
+    neurons: N,
+    spiked: Vec<bool>,
+    input: Vec<f32>,
+    bias: Vec<f32>,
+    out_offset: Vec<u32>,
+    receiver: Vec<u32>,
+    weight: Vec<f32>,
+    delay: Vec<u16>,
+    state: Vec<u32>,
+    pending: Vec<Vec<u32>>,
+    time: u64,
+    plasticity: Option<Plasticity>,
+    stimuli: Vec<Stimulus>,
+    coordinates: Option<Vec<[f32; 3]>>,
+}
+
+impl<N> From<ModelDataV3<N>> for ModelDataV4<N> {
+    fn from(v3: ModelDataV3<N>) -> Self {
+        let n = v3.spiked.len();
+        Self {
+            neurons: v3.neurons,
+            spiked: v3.spiked,
+            input: v3.input,
+            bias: v3.bias,
+            kinds: vec![NeuronKind::Excitatory; n],
+            dale: false,
+            out_offset: v3.out_offset,
+            receiver: v3.receiver,
+            weight: v3.weight,
+            delay: v3.delay,
+            state: v3.state,
+            pending: v3.pending,
+            time: v3.time,
+            plasticity: v3.plasticity,
+            stimuli: v3.stimuli,
+            coordinates: v3.coordinates,
+        }
+    }
+}
+
+/// Format version 2, before neuron coordinates
+#[derive(Deserialize)]
+struct ModelDataV2<N> {
+    neurons: N,
+    spiked: Vec<bool>,
+    input: Vec<f32>,
+    bias: Vec<f32>,
+    out_offset: Vec<u32>,
+    receiver: Vec<u32>,
+    weight: Vec<f32>,
+    delay: Vec<u16>,
+    state: Vec<u32>,
+    pending: Vec<Vec<u32>>,
+    time: u64,
+    plasticity: Option<Plasticity>,
+    stimuli: Vec<Stimulus>,
+}
+
+impl<N> From<ModelDataV2<N>> for ModelDataV3<N> {
+    fn from(v2: ModelDataV2<N>) -> Self {
+        Self {
+            neurons: v2.neurons,
+            spiked: v2.spiked,
+            input: v2.input,
+            bias: v2.bias,
+            out_offset: v2.out_offset,
+            receiver: v2.receiver,
+            weight: v2.weight,
+            delay: v2.delay,
+            state: v2.state,
+            pending: v2.pending,
+            time: v2.time,
+            plasticity: v2.plasticity,
+            stimuli: v2.stimuli,
+            coordinates: None,
+        }
+    }
+}
+
+/// Format version 1, before external inputs
+#[derive(Deserialize)]
+struct ModelDataV1<N> {
+    neurons: N,
+    spiked: Vec<bool>,
+    out_offset: Vec<u32>,
+    receiver: Vec<u32>,
+    weight: Vec<f32>,
+    delay: Vec<u16>,
+    state: Vec<u32>,
+    pending: Vec<Vec<u32>>,
+    time: u64,
+    plasticity: Option<Plasticity>,
+}
+
+impl<N> From<ModelDataV1<N>> for ModelDataV2<N> {
+    fn from(v1: ModelDataV1<N>) -> Self {
+        let n = v1.spiked.len();
+        Self {
+            neurons: v1.neurons,
+            spiked: v1.spiked,
+            input: vec![0.0; n],
+            bias: vec![0.0; n],
+            out_offset: v1.out_offset,
+            receiver: v1.receiver,
+            weight: v1.weight,
+            delay: v1.delay,
+            state: v1.state,
+            pending: v1.pending,
+            time: v1.time,
+            plasticity: v1.plasticity,
+            stimuli: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RonFileRef<'a, N> {
+    format: &'static str,
+    version: u32,
+    model: ModelDataRef<'a, N>,
+}
+
+/// Read first, to pick the loader for the version
+#[derive(Deserialize)]
+struct RonHeader {
+    format: String,
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct RonFile<D> {
+    model: D,
+}
+
+/// Encoded model data of a known version
+trait Payload {
+    fn decode<T: DeserializeOwned>(&self) -> Result<T, PersistError>;
+}
+
+struct RonPayload<'a>(&'a str);
+
+impl Payload for RonPayload<'_> {
+    fn decode<T: DeserializeOwned>(&self) -> Result<T, PersistError> {
+        Ok(ron::from_str::<RonFile<T>>(self.0)?.model)
+    }
+}
+
+struct BinaryPayload<'a>(&'a [u8]);
+
+impl Payload for BinaryPayload<'_> {
+    fn decode<T: DeserializeOwned>(&self) -> Result<T, PersistError> {
+        Ok(bincode::serde::decode_from_slice(self.0, bincode::config::standard())?.0)
+    }
+}
+
+/// Decode data saved as `version` and bring it up to the current layout.
+fn upgrade<N: DeserializeOwned>(
+    version: u32,
+    payload: impl Payload,
+) -> Result<ModelData<N>, PersistError> {
+    Ok(match version {
+        1 => {
+            let v2 = ModelDataV2::from(payload.decode::<ModelDataV1<N>>()?);
+            ModelDataV5::from(ModelDataV4::from(ModelDataV3::from(v2))).into()
+        }
+        2 => {
+            let v3 = ModelDataV3::from(payload.decode::<ModelDataV2<N>>()?);
+            ModelDataV5::from(ModelDataV4::from(v3)).into()
+        }
+        3 => ModelDataV5::from(ModelDataV4::from(payload.decode::<ModelDataV3<N>>()?)).into(),
+        4 => ModelDataV5::from(payload.decode::<ModelDataV4<N>>()?).into(),
+        5 => payload.decode::<ModelDataV5<N>>()?.into(),
+        6 => payload.decode()?,
+        v => return Err(PersistError::UnsupportedVersion(v)),
+    })
+}
+
+impl<N: NeuronModel> ModelData<N> {
+    fn into_model(self) -> Result<Model<N>, PersistError> {
+        let n = self.neurons.len();
+        let e = self.receiver.len();
+        // The reverse index and key lookup below need sound CSR arrays and
+        // keys, everything else is left to `validate`.
+        let topology = Topology {
+            out_offset: self.out_offset,
+            receiver: self.receiver,
+            weight: self.weight,
+            delay: self.delay,
+        };
+        if let Some(violation) = topology.csr_violation(n) {
+            return Err(PersistError::Malformed(violation));
+        }
+
+        let mut structure = self.structure;
+        if let Some(structure) = &mut structure {
+            if structure.neuron_keys.len() != n || structure.synapse_keys.len() != e {
+                return Err(PersistError::Malformed("keys differ in length"));
+            }
+            if !structure.neuron_keys.is_sorted() {
+                return Err(PersistError::Malformed("neuron keys out of order"));
+            }
+            structure.rebuild_index();
+        }
+
+        let mut plasticity = self.plasticity;
+        if let Some(plasticity) = &mut plasticity {
+            plasticity.index = ReverseIndex::new(&topology.out_offset, &topology.receiver);
+        }
+
+        let model = Model {
+            topology: Arc::new(topology),
+            state: SimState {
+                neurons: self.neurons,
+                spiked: self.spiked,
+                input: self.input,
+                in_flight: self.state,
+                pending: self.pending,
+                time: self.time,
+            },
+            bias: self.bias,
+            kinds: self.kinds,
+            dale: self.dale,
+            plasticity,
+            monitors: Monitors::default(),
+            stimuli: self.stimuli,
+            coordinates: self.coordinates,
+            populations: self.populations,
+            threads: None,
+            structure,
+        };
+        let report = model.validate();
+        if report.is_valid() {
+            Ok(model)
+        } else {
+            Err(PersistError::Invalid(report))
+        }
+    }
+}
+
+impl<N: NeuronModel + Serialize + DeserializeOwned> Model<N> {
+    /// Everything that gets saved, or [`PersistError::PendingEdits`] if
+    /// edits are waiting for compaction.
+    fn data(&self) -> Result<ModelDataRef<'_, N>, PersistError> {
+        if self.has_pending_edits() {
+            return Err(PersistError::PendingEdits);
+        }
+        Ok(ModelDataRef {
+            neurons: &self.state.neurons,
+            spiked: &self.state.spiked,
+            input: &self.state.input,
+            bias: &self.bias,
+            kinds: &self.kinds,
+            dale: self.dale,
+            out_offset: &self.topology.out_offset,
+            receiver: &self.topology.receiver,
+            weight: &self.topology.weight,
+            delay: &self.topology.delay,
+            state: &self.state.in_flight,
+            pending: &self.state.pending,
+            time: self.state.time,
+            plasticity: self.plasticity.as_ref(),
+            stimuli: &self.stimuli,
+            coordinates: self.coordinates.as_deref(),
+            populations: &self.populations,
+            structure: self.structure.as_ref(),
+        })
+    }
+
+    /// Serialize topology, parameters and dynamic state as RON.
+    ///
+    /// Fails with [`PersistError::PendingEdits`] until the model is compacted.
+    pub fn to_ron(&self) -> Result<String, PersistError> {
+        let file = RonFileRef {
+            format: FORMAT_NAME,
+            version: FORMAT_VERSION,
+            model: self.data()?,
+        };
+        Ok(ron::ser::to_string_pretty(
+            &file,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, PersistError> {
+        let header: RonHeader = ron::from_str(text)?;
+        if header.format != FORMAT_NAME {
+            return Err(PersistError::NotAModel);
+        }
+        upgrade::<N>(header.version, RonPayload(text))?.into_model()
+    }
+
+    /// Serialize topology, parameters and dynamic state in the compact binary format.
+    ///
+    /// Fails with [`PersistError::PendingEdits`] until the model is compacted.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, PersistError> {
+        let data = self.data()?;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        let payload = bincode::serde::encode_to_vec(data, bincode::config::standard())?;
+        bytes.extend_from_slice(&payload);
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PersistError> {
+        if bytes.len() < 8 || &bytes[..4] != MAGIC {
+            return Err(PersistError::NotAModel);
+        }
+        let version = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+        upgrade::<N>(version, BinaryPayload(&bytes[8..]))?.into_model()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>, format: Format) -> Result<(), PersistError> {
+        match format {
+            Format::Ron => fs::write(path, self.to_ron()?)?,
+            Format::Binary => fs::write(path, self.to_bytes()?)?,
+        }
+        Ok(())
+    }
+
+    /// Load a file written by [`save`](Self::save), detecting its format.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PersistError> {
+        let bytes = fs::read(path)?;
+        if bytes.starts_with(MAGIC) {
+            return Self::from_bytes(&bytes);
+        }
+        let text = std::str::from_utf8(&bytes).map_err(|_| PersistError::NotAModel)?;
+        Self::from_ron(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MAGIC, PersistError};
+    use crate::{Lif, Model, NeuronKind, Stimulus, Violation, Waveform, WeightDist};
+
+    /// Saving waits for compaction, and loading rejects what `validate` rejects.
+    #[test]
+    fn save_needs_compaction_and_load_validates() {
+        let weights = WeightDist::Uniform {
+            low: 0.1,
+            high: 0.5,
+        };
+        let mut model = Model::watts_strogatz(30, 4, 0.1, weights, 1);
+        let (pre, post) = (model.neuron_key(0), model.neuron_key(1));
+        model.insert_synapse(pre, post, 0.5, 2).unwrap();
+        assert!(matches!(model.to_bytes(), Err(PersistError::PendingEdits)));
+        assert!(matches!(model.to_ron(), Err(PersistError::PendingEdits)));
+        model.compact();
+        let loaded = Model::<Lif>::from_bytes(&model.to_bytes().unwrap()).unwrap();
+        assert_eq!(loaded.synapse_count(), model.synapse_count());
+
+        model.stimuli.push(Stimulus {
+            targets: vec![99],
+            waveform: Waveform::Step { amplitude: 1.0 },
+            start: 0,
+            duration: 5,
+        });
+        model.state.pending[0].push(u32::MAX);
+        for loaded in [
+            Model::<Lif>::from_bytes(&model.to_bytes().unwrap()),
+            Model::<Lif>::from_ron(&model.to_ron().unwrap()),
+        ] {
+            let Err(PersistError::Invalid(report)) = loaded else {
+                panic!("broken model loaded");
+            };
+            let violations = report.violations();
+            assert!(violations.contains(&Violation::StimulusTargetOutOfRange {
+                stimulus: 0,
+                neuron: 99,
+            }));
+            assert!(violations.contains(&Violation::PendingOutOfRange { synapse: u32::MAX }));
+        }
+    }
+
+    /// A binary file of format version 1, before external inputs, still loads.
+    #[test]
+    fn loads_version_1() {
+        let weights = WeightDist::Uniform {
+            low: 0.2,
+            high: 0.8,
+        };
+        let mut model = Model::watts_strogatz(40, 4, 0.2, weights, 7);
+        for t in 0..30 {
+            model.add_input(t % 40, 1.5);
+            model.tick();
+        }
+        // bincode lays out a struct as the tuple of its fields
+        let topology = &model.topology;
+        let v1 = (
+            &model.state.neurons,
+            &model.state.spiked,
+            &topology.out_offset,
+            &topology.receiver,
+            &topology.weight,
+            &topology.delay,
+            &model.state.in_flight,
+            &model.state.pending,
+            model.state.time,
+            None::<()>,
+        );
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend(bincode::serde::encode_to_vec(v1, bincode::config::standard()).unwrap());
+
+        let mut loaded = Model::<Lif>::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.time(), model.time());
+        assert!((0..40).all(|i| loaded.kind(i) == NeuronKind::Excitatory));
+        assert!((0..40).all(|i| loaded.bias(i) == 0.0));
+        for _ in 0..50 {
+            model.tick();
+            loaded.tick();
+            assert_eq!(loaded.spiked(), model.spiked());
+        }
+    }
+}
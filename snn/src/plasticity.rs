@@ -0,0 +1,386 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{NeuronKind, csc::ReverseIndex, neuron::retain_mask};
+
+/// How the size of a weight change depends on the current weight.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StdpRule {
+    /// Changes are independent of the weight, which is clamped to its bounds.
+    Additive,
+    /// Potentiation scales with `w_max - w`, depression with `w - w_min`.
+    Multiplicative,
+}
+
+/// Pair-based spike-timing-dependent plasticity with exponential traces.
+///
+/// Every spike bumps a trace of its neuron by one; traces decay with their time
+/// constant each tick. When a neuron spikes its incoming synapses are potentiated
+/// by `a_plus` times the presynaptic trace and its outgoing synapses are
+/// depressed by `a_minus` times the postsynaptic trace.
+///
+/// Timing is measured at spike emission, axonal delays are not taken into account.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Stdp {
+    /// Potentiation amplitude
+    pub a_plus: f32,
+    /// Depression amplitude
+    pub a_minus: f32,
+    /// Time constant of the presynaptic trace in ticks
+    pub tau_plus: f32,
+    /// Time constant of the postsynaptic trace in ticks
+    pub tau_minus: f32,
+    /// Lower weight bound
+    pub w_min: f32,
+    /// Upper weight bound
+    pub w_max: f32,
+    pub rule: StdpRule,
+}
+
+impl Default for Stdp {
+    fn default() -> Self {
+        Self {
+            a_plus: 0.01,
+            a_minus: 0.012,
+            tau_plus: 20.0,
+            tau_minus: 20.0,
+            w_min: 0.0,
+            w_max: 1.0,
+            rule: StdpRule::Additive,
+        }
+    }
+}
+
+impl Stdp {
+    fn potentiation(&self, w: f32, pre_trace: f32) -> f32 {
+        match self.rule {
+            StdpRule::Additive => self.a_plus * pre_trace,
+            StdpRule::Multiplicative => self.a_plus * (self.w_max - w) * pre_trace,
+        }
+    }
+
+    fn depression(&self, w: f32, post_trace: f32) -> f32 {
+        match self.rule {
+            StdpRule::Additive => self.a_minus * post_trace,
+            StdpRule::Multiplicative => self.a_minus * (w - self.w_min) * post_trace,
+        }
+    }
+}
+
+/// Three-factor learning on top of [`Stdp`].
+///
+/// STDP coincidences accumulate in a decaying eligibility trace per synapse
+/// instead of changing the weight. Weights only move when a reward is delivered,
+/// by `learning_rate * reward * eligibility`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RewardModulation {
+    /// Time constant of the eligibility trace in ticks
+    pub tau_e: f32,
+    pub learning_rate: f32,
+}
+
+impl Default for RewardModulation {
+    fn default() -> Self {
+        Self {
+            tau_e: 200.0,
+            learning_rate: 1.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Eligibility {
+    pub(crate) modulation: RewardModulation,
+    /// Per-tick decay of the eligibility trace
+    decay: f32,
+    /// Eligibility trace for each synapse
+    pub(crate) trace: Vec<f32>,
+}
+
+impl Eligibility {
+    fn new(modulation: RewardModulation, synapses: usize) -> Self {
+        Self {
+            modulation,
+            decay: (-1.0 / modulation.tau_e).exp(),
+            trace: vec![0.0; synapses],
+        }
+    }
+}
+
+/// Learning state attached to a model while STDP is enabled.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Plasticity {
+    pub(crate) stdp: Stdp,
+    /// Per-tick decay of the presynaptic trace
+    decay_plus: f32,
+    /// Per-tick decay of the postsynaptic trace
+    decay_minus: f32,
+    /// Presynaptic trace x for each neuron
+    pub(crate) pre_trace: Vec<f32>,
+    /// Postsynaptic trace y for each neuron
+    pub(crate) post_trace: Vec<f32>,
+    /// Derived from the topology, rebuilt after loading
+    #[serde(skip)]
+    pub(crate) index: ReverseIndex,
+    /// Present for reward-modulated learning
+    pub(crate) eligibility: Option<Eligibility>,
+}
+
+impl Plasticity {
+    pub(crate) fn new(
+        stdp: Stdp,
+        modulation: Option<RewardModulation>,
+        out_offset: &[u32],
+        receiver: &[u32],
+    ) -> Self {
+        let n = out_offset.len() - 1;
+        Self {
+            eligibility: modulation.map(|m| Eligibility::new(m, receiver.len())),
+            stdp,
+            decay_plus: (-1.0 / stdp.tau_plus).exp(),
+            decay_minus: (-1.0 / stdp.tau_minus).exp(),
+            pre_trace: vec![0.0; n],
+            post_trace: vec![0.0; n],
+            index: ReverseIndex::new(out_offset, receiver),
+        }
+    }
+
+    /// Start traces for a neuron appended to the model.
+    pub(crate) fn push_neuron(&mut self) {
+        self.pre_trace.push(0.0);
+        self.post_trace.push(0.0);
+    }
+
+    /// Follow a compaction of the model: keep the traces of the neurons in
+    /// `keep`, and take the eligibility of each new synapse from the old one
+    /// in `sources`, `ADDED` for fresh synapses.
+    pub(crate) fn restructure(
+        &mut self,
+        keep: &[bool],
+        sources: &[u32],
+        out_offset: &[u32],
+        receiver: &[u32],
+    ) {
+        retain_mask(&mut self.pre_trace, keep);
+        retain_mask(&mut self.post_trace, keep);
+        if let Some(eligibility) = &mut self.eligibility {
+            eligibility.trace = sources
+                .iter()
+                .map(|&j| eligibility.trace.get(j as usize).copied().unwrap_or(0.0))
+                .collect();
+        }
+        self.index = ReverseIndex::new(out_offset, receiver);
+    }
+
+    /// Apply the weight changes caused by the spikes of the current tick.
+    /// With `dale` kinds the rule works on magnitudes, keeping each sign.
+    pub(crate) fn update(
+        &mut self,
+        spiked: &[bool],
+        out_offset: &[u32],
+        receiver: &[u32],
+        weight: &mut [f32],
+        dale: Option<&[NeuronKind]>,
+    ) {
+        for x in &mut self.pre_trace {
+            *x *= self.decay_plus;
+        }
+        for y in &mut self.post_trace {
+            *y *= self.decay_minus;
+        }
+        if let Some(eligibility) = &mut self.eligibility {
+            for e in &mut eligibility.trace {
+                *e *= eligibility.decay;
+            }
+        }
+
+        let Self {
+            stdp,
+            pre_trace,
+            post_trace,
+            index,
+            eligibility,
+            ..
+        } = self;
+
+        let sign = |pre: usize| dale.map_or(1.0, |kinds| kinds[pre].sign());
+
+        // Without modulation changes go straight to the weight
+        let mut apply = |j: usize, s: f32, dw: f32, weight: &mut [f32]| match eligibility {
+            Some(eligibility) => eligibility.trace[j] += dw,
+            None => weight[j] = s * (s * weight[j] + dw).clamp(stdp.w_min, stdp.w_max),
+        };
+
+        for i in 0..spiked.len() {
+            if !spiked[i] {
+                continue;
+            }
+
+            // Post after pre: potentiate incoming synapses
+            for (&j, &pre) in index.incoming(i).iter().zip(index.senders(i)) {
+                let (j, pre) = (j as usize, pre as usize);
+                let s = sign(pre);
+                let dw = stdp.potentiation(s * weight[j], pre_trace[pre]);
+                apply(j, s, dw, weight);
+            }
+
+            // Pre after post: depress outgoing synapses
+            let start = out_offset[i] as usize;
+            let end = out_offset[i + 1] as usize;
+            let s = sign(i);
+            for j in start..end {
+                let post = receiver[j] as usize;
+                let dw = stdp.depression(s * weight[j], post_trace[post]);
+                apply(j, s, -dw, weight);
+            }
+        }
+
+        // Bump traces after the updates, so simultaneous spikes cancel out
+        for (i, _) in spiked.iter().enumerate().filter(|(_, s)| **s) {
+            self.pre_trace[i] += 1.0;
+            self.post_trace[i] += 1.0;
+        }
+    }
+
+    /// Turn the accumulated eligibility into weight changes. Synapses without
+    /// a change keep their weight, even outside the bounds.
+    /// Does nothing without reward modulation.
+    pub(crate) fn reward(
+        &mut self,
+        reward: f32,
+        out_offset: &[u32],
+        weight: &mut [f32],
+        dale: Option<&[NeuronKind]>,
+    ) {
+        let Some(eligibility) = &self.eligibility else {
+            return;
+        };
+        let scale = eligibility.modulation.learning_rate * reward;
+        for (pre, range) in out_offset.windows(2).enumerate() {
+            let s = dale.map_or(1.0, |kinds| kinds[pre].sign());
+            let range = range[0] as usize..range[1] as usize;
+            for (w, &e) in weight[range.clone()]
+                .iter_mut()
+                .zip(&eligibility.trace[range])
+            {
+                let dw = scale * e;
+                if dw != 0.0 {
+                    *w = s * (s * *w + dw).clamp(self.stdp.w_min, self.stdp.w_max);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Model, ModelBuilder, NeuronId, NeuronParams, RewardModulation, Stdp, StdpRule};
+
+    /// Weight of a 0.5 synapse from neuron 0 to neuron 1 after one spike of
+    /// each, three ticks apart, `pre_first` or the other way round.
+    fn pair(stdp: Stdp, pre_first: bool) -> f32 {
+        let mut builder = ModelBuilder::new();
+        builder.add_neurons(2, NeuronParams::default());
+        builder.connect(NeuronId(0), NeuronId(1), 0.5);
+        let mut model: Model = builder.build();
+        model.enable_stdp(stdp);
+        let (first, second) = if pre_first { (0, 1) } else { (1, 0) };
+        for t in 0..6 {
+            match t {
+                0 => model.add_input(first, 2.0),
+                3 => model.add_input(second, 2.0),
+                _ => {}
+            }
+            model.tick();
+        }
+        model.synapse_weights()[0]
+    }
+
+    /// Equal up to rounding in the trace decay.
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    /// Pre before post potentiates by `a_plus` times the trace, post before pre depresses.
+    #[test]
+    fn additive_signs() {
+        let stdp = Stdp::default();
+        let trace = (-3.0f32 / 20.0).exp();
+        let potentiated = pair(stdp, true);
+        assert!(
+            close(potentiated, 0.5 + stdp.a_plus * trace),
+            "{potentiated}"
+        );
+        let depressed = pair(stdp, false);
+        assert!(close(depressed, 0.5 - stdp.a_minus * trace), "{depressed}");
+    }
+
+    /// The multiplicative rule scales each change by the distance to the bound it heads for.
+    #[test]
+    fn multiplicative_signs() {
+        let stdp = Stdp {
+            rule: StdpRule::Multiplicative,
+            w_min: 0.2,
+            w_max: 1.5,
+            ..Stdp::default()
+        };
+        let trace = (-3.0f32 / 20.0).exp();
+        let potentiated = pair(stdp, true);
+        assert!(
+            close(potentiated, 0.5 + stdp.a_plus * 1.0 * trace),
+            "{potentiated}"
+        );
+        let depressed = pair(stdp, false);
+        assert!(
+            close(depressed, 0.5 - stdp.a_minus * 0.3 * trace),
+            "{depressed}"
+        );
+    }
+
+    /// Large steps stop at `w_min` and `w_max` under both rules.
+    #[test]
+    fn weights_stay_within_bounds() {
+        let stdp = Stdp {
+            a_plus: 1.0,
+            a_minus: 1.0,
+            w_min: 0.4,
+            w_max: 0.6,
+            ..Stdp::default()
+        };
+        assert_eq!(pair(stdp, true), 0.6);
+        assert_eq!(pair(stdp, false), 0.4);
+        let multiplicative = Stdp {
+            rule: StdpRule::Multiplicative,
+            a_plus: 50.0,
+            a_minus: 50.0,
+            ..stdp
+        };
+        assert_eq!(pair(multiplicative, true), 0.6);
+        assert_eq!(pair(multiplicative, false), 0.4);
+    }
+
+    /// 0 -> 1 inhibitory and 1 -> 2 excitatory, learning by reward.
+    fn rewarded() -> Model {
+        let mut builder = ModelBuilder::new();
+        builder.add_neurons(3, NeuronParams::default());
+        builder.connect(NeuronId(0), NeuronId(1), -0.5);
+        builder.connect(NeuronId(1), NeuronId(2), 0.5);
+        let mut model = builder.build();
+        model.enable_reward_modulated_stdp(Stdp::default(), RewardModulation::default());
+        model
+    }
+
+    /// A reward only moves synapses with eligibility, out-of-bounds weights
+    /// elsewhere stay put.
+    #[test]
+    fn reward_leaves_ineligible_synapses_alone() {
+        let mut model = rewarded();
+        model.deliver_reward(0.0);
+        model.deliver_reward(1.0);
+        assert_eq!(model.synapse_weights(), [-0.5, 0.5]);
+
+        let plasticity = model.plasticity.as_mut().unwrap();
+        plasticity.eligibility.as_mut().unwrap().trace[1] = 0.1;
+        model.deliver_reward(2.0);
+        assert_eq!(model.synapse_weights(), [-0.5, 0.7]);
+    }
+}
@@ -0,0 +1,127 @@
+//! Named groups of neurons and projections between them.
+//!
+//! A population is a contiguous range of neuron indices under a name. The
+//! builder creates one per call to
+//! [`add_population`](crate::ModelBuilder::add_population), and
+//! [`project`](crate::ModelBuilder::project) connects two of them by a [`Connectivity`] rule.
+//! The model keeps the populations for stimulation, monitoring and rendering.
+
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{DEFAULT_SYNAPSE_DELAY, Error, Model, NeuronModel, WeightDist};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Population {
+    pub name: String,
+    pub range: Range<u32>,
+}
+
+impl Population {
+    pub fn len(&self) -> usize {
+        self.range.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty()
+    }
+
+    /// Model indices of the neurons, in order.
+    pub fn neurons(&self) -> Range<u32> {
+        self.range.clone()
+    }
+
+    /// Model index of the `k`-th neuron of the population.
+    ///
+    /// Panics if the population has fewer than `k + 1` neurons.
+    pub fn neuron(&self, k: usize) -> u32 {
+        assert!(k < self.len(), "population {} has no neuron {k}", self.name);
+        self.range.start + k as u32
+    }
+
+    pub fn contains(&self, neuron: u32) -> bool {
+        self.range.contains(&neuron)
+    }
+}
+
+/// Which pairs of a projection get a synapse.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Connectivity {
+    /// The k-th neuron of the source to the k-th of the target
+    OneToOne,
+    AllToAll,
+    /// Every pair independently with this probability
+    FixedProbability(f64),
+    /// Every target neuron from this many distinct random sources
+    FixedInDegree(usize),
+}
+
+/// How to connect one population to another.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Projection {
+    pub connectivity: Connectivity,
+    pub weights: WeightDist,
+    pub delay: u16,
+    /// Whether a neuron may connect to itself when a population projects onto itself
+    pub autapses: bool,
+}
+
+impl Projection {
+    pub fn new(connectivity: Connectivity, weights: WeightDist) -> Self {
+        Self {
+            connectivity,
+            weights,
+            delay: DEFAULT_SYNAPSE_DELAY,
+            autapses: false,
+        }
+    }
+
+    /// Panics if `delay` is 0.
+    pub fn with_delay(self, delay: u16) -> Self {
+        self.try_with_delay(delay).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like [`with_delay`](Self::with_delay), failing with [`Error::ZeroDelay`]
+    /// if `delay` is 0.
+    pub fn try_with_delay(mut self, delay: u16) -> Result<Self, Error> {
+        if delay == 0 {
+            return Err(Error::ZeroDelay);
+        }
+        self.delay = delay;
+        Ok(self)
+    }
+
+    pub fn with_autapses(mut self, autapses: bool) -> Self {
+        self.autapses = autapses;
+        self
+    }
+}
+
+impl<N: NeuronModel> Model<N> {
+    pub fn populations(&self) -> &[Population] {
+        &self.populations
+    }
+
+    pub fn population(&self, name: &str) -> Option<&Population> {
+        self.populations.iter().find(|p| p.name == name)
+    }
+
+    /// First population containing `neuron`.
+    pub fn population_of(&self, neuron: u32) -> Option<&Population> {
+        self.populations.iter().find(|p| p.contains(neuron))
+    }
+
+    /// Name a range of existing neurons. Populations may overlap.
+    ///
+    /// Panics if the name is taken, the range is reversed or it ends past the
+    /// last neuron.
+    pub fn define_population(&mut self, name: impl Into<String>, range: Range<u32>) -> &Population {
+        let name = name.into();
+        if let Err(e) = self.check_population(&name, &range) {
+            panic!("{e}");
+        }
+        self.populations.push(Population { name, range });
+        self.populations.last().unwrap()
+    }
+}
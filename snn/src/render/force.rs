@@ -0,0 +1,403 @@
+//! Fruchterman-Reingold style force-directed layout.
+//!
+//! Synapses pull their neurons together like springs, all neurons repel each
+//! other. Repulsion is approximated with a Barnes-Hut quadtree, which keeps an
+//! iteration at O(n log n).
+
+use std::ops::Range;
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use super::{NeuronPosition, SynapsePosition, synapse_positions};
+use crate::{Model, NeuronModel};
+
+/// Depth at which the quadtree stops splitting, guards against coincident points
+const MAX_TREE_DEPTH: u32 = 24;
+
+#[derive(Clone, Copy, Debug)]
+pub struct LayoutConfig {
+    /// Seed for the initial placement, equal seeds give equal layouts
+    pub seed: u64,
+    /// Upper bound on the number of iterations
+    pub iterations: usize,
+    /// Stop early once no neuron moves more than this fraction of `edge_length`
+    pub tolerance: f32,
+    /// Preferred distance between connected neurons
+    pub edge_length: f32,
+    /// Barnes-Hut opening angle, 0 computes exact repulsion
+    pub theta: f32,
+    /// Radius of every neuron in the output
+    pub node_radius: f32,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        // Edge length and radius match the scale of Graphviz' neato output
+        Self {
+            seed: 0,
+            iterations: 300,
+            tolerance: 1e-3,
+            edge_length: 1.0,
+            theta: 0.8,
+            node_radius: 0.025,
+        }
+    }
+}
+
+/// Lay the model out in pure Rust.
+///
+/// Returns (neuron_positions, synapse_positions)
+pub fn force_layout<N: NeuronModel>(
+    model: &Model<N>,
+    config: &LayoutConfig,
+) -> (Vec<NeuronPosition>, Vec<SynapsePosition>) {
+    let n = model.neuron_count();
+    let k = config.edge_length;
+
+    // Start from a random square that fits all neurons at roughly edge distance
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let side = (n as f32).sqrt().max(1.0) * k;
+    let mut pos: Vec<[f32; 2]> = (0..n)
+        .map(|_| {
+            [
+                rng.random_range(-0.5..0.5) * side,
+                rng.random_range(-0.5..0.5) * side,
+            ]
+        })
+        .collect();
+
+    relax(model, config, &mut pos, side);
+
+    // Center around the origin, and scale so synapses have `edge_length` on average.
+    // Global repulsion otherwise stretches large graphs well beyond it.
+    if n > 0 {
+        let cx = pos.iter().map(|p| p[0]).sum::<f32>() / n as f32;
+        let cy = pos.iter().map(|p| p[1]).sum::<f32>() / n as f32;
+        let (mut total, mut count) = (0.0f32, 0usize);
+        for src in 0..n {
+            let start = model.topology.out_offset[src] as usize;
+            let end = model.topology.out_offset[src + 1] as usize;
+            for &tgt in &model.topology.receiver[start..end] {
+                let (p, q) = (pos[src], pos[tgt as usize]);
+                total += ((p[0] - q[0]).powi(2) + (p[1] - q[1]).powi(2)).sqrt();
+                count += 1;
+            }
+        }
+        let scale = if total > 0.0 {
+            k * count as f32 / total
+        } else {
+            1.0
+        };
+        for p in &mut pos {
+            p[0] = (p[0] - cx) * scale;
+            p[1] = (p[1] - cy) * scale;
+        }
+    }
+
+    let neuron_positions: Vec<NeuronPosition> = pos
+        .into_iter()
+        .map(|center| NeuronPosition {
+            center,
+            radius: config.node_radius,
+        })
+        .collect();
+    let synapse_positions = synapse_positions(model, &neuron_positions);
+    (neuron_positions, synapse_positions)
+}
+
+/// Move the neurons at `pos` under the forces, starting from steps of a tenth
+/// of `side`. Returns the number of iterations run.
+fn relax<N: NeuronModel>(
+    model: &Model<N>,
+    config: &LayoutConfig,
+    pos: &mut [[f32; 2]],
+    side: f32,
+) -> usize {
+    let n = pos.len();
+    let k = config.edge_length;
+    let mut disp = vec![[0.0f32; 2]; n];
+    let mut temperature = 0.1 * side;
+    let cooling = 0.01f32.powf(1.0 / config.iterations.max(1) as f32);
+
+    for iteration in 0..config.iterations {
+        if n < 2 {
+            return iteration;
+        }
+        disp.fill([0.0, 0.0]);
+
+        // Repulsion between all pairs: k² / d
+        let tree = QuadTree::new(pos);
+        for (i, d) in disp.iter_mut().enumerate() {
+            *d = tree.repulsion(pos, i, k * k, config.theta);
+        }
+
+        // Attraction along synapses: d² / k
+        for src in 0..n {
+            let start = model.topology.out_offset[src] as usize;
+            let end = model.topology.out_offset[src + 1] as usize;
+            for &tgt in &model.topology.receiver[start..end] {
+                let tgt = tgt as usize;
+                if tgt == src {
+                    continue;
+                }
+                let dx = pos[tgt][0] - pos[src][0];
+                let dy = pos[tgt][1] - pos[src][1];
+                let dist = (dx * dx + dy * dy).sqrt();
+                let f = dist / k;
+                disp[src][0] += dx * f;
+                disp[src][1] += dy * f;
+                disp[tgt][0] -= dx * f;
+                disp[tgt][1] -= dy * f;
+            }
+        }
+
+        // Move, limited by the temperature
+        let mut max_step = 0.0f32;
+        for (p, d) in pos.iter_mut().zip(&disp) {
+            let len = (d[0] * d[0] + d[1] * d[1]).sqrt();
+            if len <= f32::EPSILON {
+                continue;
+            }
+            let step = len.min(temperature);
+            p[0] += d[0] / len * step;
+            p[1] += d[1] / len * step;
+            max_step = max_step.max(step);
+        }
+
+        temperature *= cooling;
+        if max_step < config.tolerance * k {
+            return iteration + 1;
+        }
+    }
+    config.iterations
+}
+
+struct QuadNode {
+    /// Center of mass of the points below this node
+    com: [f32; 2],
+    mass: f32,
+    /// Edge length of the node's square
+    size: f32,
+    /// Index of the first of four children, 0 for leaves (the root is never a child)
+    children: u32,
+    /// Points of a leaf, as a range into `QuadTree::points`
+    points: (u32, u32),
+}
+
+/// Square area of a quadtree node.
+#[derive(Clone, Copy)]
+struct Square {
+    /// Corner with the smallest coordinates
+    origin: [f32; 2],
+    size: f32,
+}
+
+struct QuadTree {
+    nodes: Vec<QuadNode>,
+    /// Point indices, grouped by leaf
+    points: Vec<u32>,
+}
+
+impl QuadTree {
+    fn new(pos: &[[f32; 2]]) -> Self {
+        let (mut min, mut max) = ([f32::MAX; 2], [f32::MIN; 2]);
+        for p in pos {
+            for a in 0..2 {
+                min[a] = min[a].min(p[a]);
+                max[a] = max[a].max(p[a]);
+            }
+        }
+        let size = (max[0] - min[0]).max(max[1] - min[1]).max(f32::EPSILON);
+
+        let mut tree = Self {
+            nodes: Vec::with_capacity(2 * pos.len()),
+            points: (0..pos.len() as u32).collect(),
+        };
+        tree.nodes.push(QuadNode {
+            com: [0.0; 2],
+            mass: 0.0,
+            size,
+            children: 0,
+            points: (0, 0),
+        });
+        let square = Square { origin: min, size };
+        tree.build(pos, 0, square, 0..pos.len(), 0);
+        tree
+    }
+
+    /// Fill node `node` covering `square` with the points in `points[range]`.
+    fn build(
+        &mut self,
+        pos: &[[f32; 2]],
+        node: usize,
+        square: Square,
+        range: Range<usize>,
+        depth: u32,
+    ) {
+        let Range { start: lo, end: hi } = range;
+        let mut com = [0.0f32; 2];
+        for &i in &self.points[lo..hi] {
+            com[0] += pos[i as usize][0];
+            com[1] += pos[i as usize][1];
+        }
+        let mass = (hi - lo) as f32;
+        self.nodes[node].com = [com[0] / mass, com[1] / mass];
+        self.nodes[node].mass = mass;
+
+        if hi - lo <= 1 || depth >= MAX_TREE_DEPTH {
+            self.nodes[node].points = (lo as u32, hi as u32);
+            return;
+        }
+
+        let half = square.size * 0.5;
+        let origin = square.origin;
+        let mid = [origin[0] + half, origin[1] + half];
+        let quadrant = |p: [f32; 2]| (p[0] >= mid[0]) as usize + 2 * (p[1] >= mid[1]) as usize;
+        self.points[lo..hi].sort_unstable_by_key(|&i| quadrant(pos[i as usize]));
+
+        let first_child = self.nodes.len();
+        self.nodes[node].children = first_child as u32;
+        for _ in 0..4 {
+            self.nodes.push(QuadNode {
+                com: [0.0; 2],
+                mass: 0.0,
+                size: half,
+                children: 0,
+                points: (0, 0),
+            });
+        }
+
+        let mut start = lo;
+        for q in 0..4 {
+            let end = start
+                + self.points[start..hi]
+                    .iter()
+                    .take_while(|&&i| quadrant(pos[i as usize]) == q)
+                    .count();
+            if end > start {
+                let child = Square {
+                    origin: [
+                        origin[0] + half * (q & 1) as f32,
+                        origin[1] + half * (q >> 1) as f32,
+                    ],
+                    size: half,
+                };
+                self.build(pos, first_child + q, child, start..end, depth + 1);
+            }
+            start = end;
+        }
+    }
+
+    /// Repulsive displacement on point `i` from all others, with strength k² / d.
+    fn repulsion(&self, pos: &[[f32; 2]], i: usize, k2: f32, theta: f32) -> [f32; 2] {
+        let p = pos[i];
+        let mut force = [0.0f32; 2];
+        let mut push = |from: [f32; 2], mass: f32, salt: usize| {
+            let mut dx = p[0] - from[0];
+            let mut dy = p[1] - from[1];
+            let mut d2 = dx * dx + dy * dy;
+            if d2 < 1e-12 {
+                // Coincident points: separate them in a deterministic direction
+                let angle = (i.wrapping_mul(31) ^ salt) as f32;
+                dx = angle.cos() * 1e-3;
+                dy = angle.sin() * 1e-3;
+                d2 = 1e-6;
+            }
+            let f = mass * k2 / d2;
+            force[0] += dx * f;
+            force[1] += dy * f;
+        };
+
+        let mut stack = vec![0usize];
+        while let Some(node) = stack.pop() {
+            let q = &self.nodes[node];
+            if q.mass == 0.0 {
+                continue;
+            }
+            if q.children == 0 {
+                for &j in &self.points[q.points.0 as usize..q.points.1 as usize] {
+                    if j as usize != i {
+                        push(pos[j as usize], 1.0, j as usize);
+                    }
+                }
+                continue;
+            }
+            let dx = p[0] - q.com[0];
+            let dy = p[1] - q.com[1];
+            let dist = (dx * dx + dy * dy).sqrt();
+            if q.size < theta * dist {
+                push(q.com, q.mass, node);
+            } else {
+                let c = q.children as usize;
+                stack.extend([c, c + 1, c + 2, c + 3]);
+            }
+        }
+        force
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LayoutConfig, force_layout, relax};
+    use crate::{Model, ModelBuilder, NeuronParams, WeightDist};
+
+    fn finite(model: &Model, config: &LayoutConfig) -> bool {
+        let (neurons, synapses) = force_layout(model, config);
+        neurons
+            .iter()
+            .all(|p| p.center.iter().all(|c| c.is_finite()))
+            && synapses
+                .iter()
+                .all(|s| s.end1.iter().chain(&s.end2).all(|c| c.is_finite()))
+    }
+
+    /// Equal seeds give equal layouts, different seeds do not.
+    #[test]
+    fn seeds_reproduce() {
+        let model = Model::erdos_renyi(40, 0.1, WeightDist::Constant(0.1), 5);
+        let config = LayoutConfig::default();
+        let centers = |seed| {
+            let (neurons, _) = force_layout(&model, &LayoutConfig { seed, ..config });
+            neurons.iter().map(|p| p.center).collect::<Vec<_>>()
+        };
+        assert_eq!(centers(1), centers(1));
+        assert_ne!(centers(1), centers(2));
+    }
+
+    /// The layout stops at `iterations`, or earlier once it has settled.
+    #[test]
+    fn iterations_are_capped() {
+        let model = Model::erdos_renyi(30, 0.1, WeightDist::Constant(0.1), 5);
+        let mut pos: Vec<[f32; 2]> = (0..30).map(|i| [i as f32, (i * i % 7) as f32]).collect();
+        let exact = LayoutConfig {
+            iterations: 17,
+            tolerance: 0.0,
+            ..LayoutConfig::default()
+        };
+        assert_eq!(relax(&model, &exact, &mut pos, 6.0), 17);
+        let loose = LayoutConfig {
+            iterations: 1000,
+            tolerance: 0.05,
+            ..LayoutConfig::default()
+        };
+        assert!(relax(&model, &loose, &mut pos, 6.0) < 1000);
+    }
+
+    /// Neurons on top of each other, without synapses or alone still get
+    /// finite positions.
+    #[test]
+    fn degenerate_models_stay_finite() {
+        let config = LayoutConfig::default();
+        let ring = Model::watts_strogatz(12, 2, 0.0, WeightDist::Constant(0.1), 1);
+        let mut pos = vec![[0.5f32; 2]; 12];
+        relax(&ring, &config, &mut pos, 1.0);
+        assert!(pos.iter().flatten().all(|c| c.is_finite()));
+        assert!(finite(&ring, &config));
+
+        for n in [0, 1, 2, 25] {
+            let mut builder = ModelBuilder::new();
+            builder.add_neurons(n, NeuronParams::default());
+            assert!(finite(&builder.build(), &config), "{n} neurons");
+        }
+    }
+}
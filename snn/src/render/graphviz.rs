@@ -0,0 +1,149 @@
+use super::{LayoutConfig, NeuronPosition, SynapsePosition, force_layout, synapse_positions};
+use crate::{Error, Model, NeuronModel};
+
+use graphviz_rust::{
+    cmd::{CommandArg, Format},
+    dot_structures::{
+        Attribute, Edge, EdgeTy, Graph, GraphAttributes, Id, Node, NodeId, Stmt, Vertex,
+    },
+    exec,
+    printer::PrinterContext,
+};
+
+/// Build the neato graph of the model: one point per neuron, one undirected line per synapse.
+fn build_graph<N: NeuronModel>(model: &Model<N>) -> Graph {
+    let mut g = Graph::DiGraph {
+        id: Id::Plain("model".to_string()),
+        strict: false,
+        stmts: Vec::new(),
+    };
+
+    g.add_stmt(Stmt::GAttribute(GraphAttributes::Graph(vec![
+        Attribute(Id::Plain("layout".into()), Id::Plain("neato".into())),
+        Attribute(Id::Plain("overlap".into()), Id::Plain("false".into())),
+        Attribute(Id::Plain("splines".into()), Id::Plain("line".into())),
+        Attribute(Id::Plain("mode".into()), Id::Plain("sgd".into())),
+    ])));
+
+    let n = model.neuron_count();
+
+    for i in 0..n {
+        let node_id = NodeId(Id::Plain(format!("n{}", i)), None);
+        let node = Node::new(
+            node_id.clone(),
+            vec![Attribute(
+                Id::Plain("shape".into()),
+                Id::Plain("point".into()),
+            )],
+        );
+        g.add_stmt(Stmt::Node(node));
+
+        let start = *model.topology.out_offset.get(i).unwrap_or(&0) as usize;
+        let end = *model
+            .topology
+            .out_offset
+            .get(i + 1)
+            .unwrap_or(&(start as u32)) as usize;
+
+        for eidx in start..end {
+            let tgt = model.topology.receiver[eidx] as usize;
+
+            let edge = Edge {
+                ty: EdgeTy::Pair(
+                    Vertex::N(node_id.clone()),
+                    Vertex::N(NodeId(Id::Plain(format!("n{}", tgt)), None)),
+                ),
+                // no label, no arrowhead
+                attributes: vec![Attribute(Id::Plain("dir".into()), Id::Plain("none".into()))],
+            };
+
+            g.add_stmt(Stmt::Edge(edge));
+        }
+    }
+
+    g
+}
+
+/// Run Graphviz, telling a missing installation apart from other failures.
+fn run_neato(graph: Graph, format: Format) -> Result<Vec<u8>, Error> {
+    let mut ctx = PrinterContext::default();
+    exec(graph, &mut ctx, vec![CommandArg::Format(format)]).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            Error::GraphvizMissing
+        } else {
+            Error::Graphviz(e)
+        }
+    })
+}
+
+pub fn to_neato_png<N: NeuronModel>(model: &Model<N>) -> Result<Vec<u8>, Error> {
+    run_neato(build_graph(model), Format::Png)
+}
+
+/// Lay the model out with Graphviz' neato.
+/// Falls back to [`force_layout`] if Graphviz can't be run or its output can't be read.
+///
+/// Returns (neuron_positions, synapse_positions)
+pub fn layout_graph_neato<N: NeuronModel>(
+    model: &Model<N>,
+) -> (Vec<NeuronPosition>, Vec<SynapsePosition>) {
+    try_layout_graph_neato(model).unwrap_or_else(|_| force_layout(model, &LayoutConfig::default()))
+}
+
+/// Lay the model out with Graphviz' neato, without a fallback.
+///
+/// Returns (neuron_positions, synapse_positions)
+pub fn try_layout_graph_neato<N: NeuronModel>(
+    model: &Model<N>,
+) -> Result<(Vec<NeuronPosition>, Vec<SynapsePosition>), Error> {
+    // 1. Ask Graphviz/neato for a plain layout
+    let bytes = run_neato(build_graph(model), Format::Plain)?;
+    let text = String::from_utf8_lossy(&bytes);
+
+    // 2. Parse "node" lines from plain output
+    // Format:
+    // node <name> <x> <y> <width> <height> <label> ...
+    let mut neuron_positions = vec![
+        NeuronPosition {
+            center: [0.0, 0.0],
+            radius: 0.0
+        };
+        model.neuron_count()
+    ];
+
+    let malformed = |line: &str| Error::Parse(format!("unexpected neato output line: {line}"));
+    for line in text.lines() {
+        let mut parts = line.split_whitespace();
+        if parts.next() != Some("node") {
+            continue;
+        }
+        let (Some(name), Some(xs), Some(ys), Some(ws), Some(hs)) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ) else {
+            return Err(malformed(line));
+        };
+        let idx = name
+            .strip_prefix('n')
+            .and_then(|idx| idx.parse::<usize>().ok())
+            .filter(|&idx| idx < neuron_positions.len())
+            .ok_or_else(|| malformed(line))?;
+        let mut fields = [0.0f32; 4];
+        for (field, text) in fields.iter_mut().zip([xs, ys, ws, hs]) {
+            *field = text.parse().map_err(|_| malformed(line))?;
+        }
+        let [x, y, w, h] = fields;
+
+        neuron_positions[idx] = NeuronPosition {
+            center: [x, y],
+            radius: (w.max(h)) * 0.5,
+        };
+    }
+
+    // 3. Derive synapse positions from neuron positions and the model's adjacency
+    let synapse_positions = synapse_positions(model, &neuron_positions);
+    Ok((neuron_positions, synapse_positions))
+}
@@ -0,0 +1,126 @@
+//! Placing neurons and synapses in 2D for drawing.
+
+mod force;
+#[cfg(feature = "graphviz-exec")]
+mod graphviz;
+
+use crate::{Model, NeuronModel};
+
+pub use force::{LayoutConfig, force_layout};
+#[cfg(feature = "graphviz-exec")]
+pub use graphviz::{layout_graph_neato, to_neato_png, try_layout_graph_neato};
+
+#[derive(Clone, Copy)]
+pub struct NeuronPosition {
+    pub center: [f32; 2],
+    pub radius: f32,
+}
+
+#[derive(Clone, Copy)]
+pub struct SynapsePosition {
+    pub end1: [f32; 2],
+    pub end2: [f32; 2],
+}
+
+/// Place neurons at the model's own coordinates if it has them, otherwise
+/// lay it out with the native force-directed engine. Default settings either way.
+///
+/// Returns (neuron_positions, synapse_positions)
+pub fn layout_graph<N: NeuronModel>(
+    model: &Model<N>,
+) -> (Vec<NeuronPosition>, Vec<SynapsePosition>) {
+    let config = LayoutConfig::default();
+    coordinate_layout(model, &config).unwrap_or_else(|| force_layout(model, &config))
+}
+
+/// Place neurons at the model's coordinates, centred on the origin,
+/// `None` if the model has none. 3D coordinates get an oblique projection,
+/// z shifts a point up and to the right.
+pub fn coordinate_layout<N: NeuronModel>(
+    model: &Model<N>,
+    config: &LayoutConfig,
+) -> Option<(Vec<NeuronPosition>, Vec<SynapsePosition>)> {
+    let projected: Vec<[f32; 2]> = model
+        .coordinates()?
+        .iter()
+        .map(|&[x, y, z]| [x + 0.5 * z, y + 0.3 * z])
+        .collect();
+
+    let (mut min, mut max) = ([f32::INFINITY; 2], [f32::NEG_INFINITY; 2]);
+    for p in &projected {
+        for axis in 0..2 {
+            min[axis] = min[axis].min(p[axis]);
+            max[axis] = max[axis].max(p[axis]);
+        }
+    }
+    let centre = [(min[0] + max[0]) * 0.5, (min[1] + max[1]) * 0.5];
+
+    let neuron_positions: Vec<NeuronPosition> = projected
+        .iter()
+        .map(|p| NeuronPosition {
+            center: [p[0] - centre[0], p[1] - centre[1]],
+            radius: config.node_radius,
+        })
+        .collect();
+    let synapse_positions = synapse_positions(model, &neuron_positions);
+    Some((neuron_positions, synapse_positions))
+}
+
+/// Colours neurons of populations get, in the order the populations were defined
+const PALETTE: [[f32; 4]; 8] = [
+    [0.12, 0.47, 0.71, 1.0],
+    [1.00, 0.50, 0.05, 1.0],
+    [0.17, 0.63, 0.17, 1.0],
+    [0.84, 0.15, 0.16, 1.0],
+    [0.58, 0.40, 0.74, 1.0],
+    [0.55, 0.34, 0.29, 1.0],
+    [0.89, 0.47, 0.76, 1.0],
+    [0.74, 0.74, 0.13, 1.0],
+];
+
+/// Colour of neurons outside every population
+const UNGROUPED: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+/// RGBA colour of each neuron by its population, cycling through a palette.
+/// Neurons in several populations take the colour of the first one.
+pub fn population_colours<N: NeuronModel>(model: &Model<N>) -> Vec<[f32; 4]> {
+    let mut colours = vec![UNGROUPED; model.neuron_count()];
+    let mut coloured = vec![false; model.neuron_count()];
+    for (k, population) in model.populations().iter().enumerate() {
+        for neuron in population.neurons() {
+            let i = neuron as usize;
+            if !coloured[i] {
+                colours[i] = PALETTE[k % PALETTE.len()];
+                coloured[i] = true;
+            }
+        }
+    }
+    colours
+}
+
+/// One line per synapse between the centers of its neurons.
+/// Keeps the original edge order in model.topology.receiver.
+fn synapse_positions<N: NeuronModel>(
+    model: &Model<N>,
+    neuron_positions: &[NeuronPosition],
+) -> Vec<SynapsePosition> {
+    let n = model.neuron_count();
+    let mut synapse_positions = Vec::with_capacity(model.topology.receiver.len());
+    for src in 0..n {
+        let start = model.topology.out_offset[src] as usize;
+        let end = model.topology.out_offset[src + 1] as usize;
+
+        let src_pos = neuron_positions[src].center;
+        for &tgt in &model.topology.receiver[start..end] {
+            let tgt_pos = neuron_positions
+                .get(tgt as usize)
+                .map_or([0.0, 0.0], |p| p.center);
+
+            synapse_positions.push(SynapsePosition {
+                end1: src_pos,
+                end2: tgt_pos,
+            });
+        }
+    }
+    synapse_positions
+}
@@ -0,0 +1,283 @@
+//! Liquid state machines: a recurrent [`Model`] as a fixed reservoir with a
+//! trained linear readout.
+//!
+//! Each step of an input sequence is presented for a few ticks, after which
+//! the exponentially filtered spike counts of the reservoir neurons form the
+//! state of that step. A [`LinearReadout`] is fitted offline to map states to
+//! targets. [`narma`] and [`delayed_recall`] generate benchmark sequences.
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::{Encoder, FitError, LinearReadout, Model, NeuronModel};
+
+/// Exponentially filtered spike counts of a set of neurons.
+#[derive(Clone, Debug)]
+pub struct SpikeFilter {
+    neurons: Vec<u32>,
+    /// Factor the traces are multiplied by every tick
+    decay: f32,
+    trace: Vec<f32>,
+}
+
+impl SpikeFilter {
+    /// Filter with time constant `tau` in ticks, 0 counts without decay.
+    pub fn new(neurons: impl IntoIterator<Item = u32>, tau: f32) -> Self {
+        let neurons: Vec<u32> = neurons.into_iter().collect();
+        Self {
+            trace: vec![0.0; neurons.len()],
+            decay: if tau > 0.0 { (-1.0 / tau).exp() } else { 1.0 },
+            neurons,
+        }
+    }
+
+    pub fn neurons(&self) -> &[u32] {
+        &self.neurons
+    }
+
+    /// Decay the traces and add the spikes of the latest tick.
+    pub fn observe<N: NeuronModel>(&mut self, model: &Model<N>) {
+        let spiked = model.spiked();
+        for (trace, &i) in self.trace.iter_mut().zip(&self.neurons) {
+            *trace *= self.decay;
+            if spiked[i as usize] {
+                *trace += 1.0;
+            }
+        }
+    }
+
+    pub fn state(&self) -> &[f32] {
+        &self.trace
+    }
+
+    pub fn reset(&mut self) {
+        self.trace.fill(0.0);
+    }
+}
+
+/// Inputs and desired outputs of each step of a sequence.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Sequence {
+    pub inputs: Vec<Vec<f32>>,
+    pub targets: Vec<Vec<f32>>,
+}
+
+impl Sequence {
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    /// Split into the first `at` steps and the rest.
+    pub fn split_at(&self, at: usize) -> (Sequence, Sequence) {
+        let (inputs_a, inputs_b) = self.inputs.split_at(at);
+        let (targets_a, targets_b) = self.targets.split_at(at);
+        (
+            Sequence {
+                inputs: inputs_a.to_vec(),
+                targets: targets_a.to_vec(),
+            },
+            Sequence {
+                inputs: inputs_b.to_vec(),
+                targets: targets_b.to_vec(),
+            },
+        )
+    }
+}
+
+/// Error of a readout on held-out sequences.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Evaluation {
+    /// Mean squared error over all outputs and scored steps
+    pub mse: f32,
+    /// Root mean squared error divided by the standard deviation of the targets
+    pub nrmse: f32,
+    /// Number of steps scored, after the washout
+    pub steps: usize,
+}
+
+/// A reservoir model driven by an encoder, with the filter that reads its state.
+pub struct Reservoir<N: NeuronModel, E: Encoder> {
+    pub model: Model<N>,
+    pub encoder: E,
+    pub filter: SpikeFilter,
+    /// Ticks each step of a sequence is presented for
+    pub ticks_per_step: u32,
+}
+
+impl<N: NeuronModel, E: Encoder> Reservoir<N, E> {
+    pub fn new(model: Model<N>, encoder: E, filter: SpikeFilter, ticks_per_step: u32) -> Self {
+        Self {
+            model,
+            encoder,
+            filter,
+            ticks_per_step,
+        }
+    }
+
+    /// Present `inputs` one after another and return the state after each step.
+    /// The reservoir carries on from where the previous run left it.
+    pub fn run(&mut self, inputs: &[Vec<f32>]) -> Vec<Vec<f32>> {
+        inputs
+            .iter()
+            .map(|input| {
+                for t in 0..self.ticks_per_step {
+                    self.encoder.apply(&mut self.model, input, t);
+                    self.model.tick();
+                    self.filter.observe(&self.model);
+                }
+                self.filter.state().to_vec()
+            })
+            .collect()
+    }
+
+    /// Run the training `sequences` and fit a readout with `ridge` penalty to
+    /// their targets, ignoring the first `washout` steps of each.
+    pub fn train(
+        &mut self,
+        sequences: &[Sequence],
+        washout: usize,
+        ridge: f32,
+    ) -> Result<LinearReadout, FitError> {
+        let outputs = sequences
+            .iter()
+            .find_map(|s| s.targets.first())
+            .map_or(0, Vec::len);
+        let mut features = Vec::new();
+        let mut targets = Vec::new();
+        for sequence in sequences {
+            let states = self.run(&sequence.inputs);
+            features.extend(states.into_iter().skip(washout));
+            targets.extend(sequence.targets.iter().skip(washout).cloned());
+        }
+        let mut readout = LinearReadout::new(self.filter.neurons.iter().copied(), outputs);
+        readout.fit(&features, &targets, ridge)?;
+        Ok(readout)
+    }
+
+    /// Run held-out `sequences` and score the predictions of `readout`.
+    pub fn evaluate(
+        &mut self,
+        readout: &LinearReadout,
+        sequences: &[Sequence],
+        washout: usize,
+    ) -> Evaluation {
+        let mut predictions = Vec::new();
+        let mut targets = Vec::new();
+        for sequence in sequences {
+            let states = self.run(&sequence.inputs);
+            predictions.extend(states.iter().skip(washout).map(|s| readout.predict(s)));
+            targets.extend(sequence.targets.iter().skip(washout).cloned());
+        }
+        evaluate(&predictions, &targets)
+    }
+}
+
+/// Compare predictions with targets, step by step.
+pub fn evaluate(predictions: &[Vec<f32>], targets: &[Vec<f32>]) -> Evaluation {
+    let values = || targets.iter().flatten().map(|&t| t as f64);
+    let count = values().count();
+    if count == 0 {
+        return Evaluation {
+            mse: 0.0,
+            nrmse: 0.0,
+            steps: 0,
+        };
+    }
+    let mean = values().sum::<f64>() / count as f64;
+    let variance = values().map(|t| (t - mean) * (t - mean)).sum::<f64>() / count as f64;
+    let squared_error: f64 = predictions
+        .iter()
+        .flatten()
+        .zip(targets.iter().flatten())
+        .map(|(&p, &t)| (p as f64 - t as f64).powi(2))
+        .sum();
+    let mse = squared_error / count as f64;
+    Evaluation {
+        mse: mse as f32,
+        nrmse: if variance > 0.0 {
+            (mse / variance).sqrt() as f32
+        } else {
+            f32::INFINITY
+        },
+        steps: targets.len(),
+    }
+}
+
+/// NARMA benchmark of the given `order` (usually 10) with inputs drawn
+/// uniformly from [0, 0.5]:
+///
+/// y(t+1) = 0.3 y(t) + 0.05 y(t) Σᵢ y(t-i) + 1.5 u(t-order+1) u(t) + 0.1
+///
+/// The target of step t is y(t+1). Orders much above 10 can diverge.
+///
+/// Panics if `order` is 0.
+pub fn narma(len: usize, order: usize, seed: u64) -> Sequence {
+    assert!(order >= 1, "NARMA order must be at least 1");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let u: Vec<f64> = (0..len).map(|_| rng.random_range(0.0..0.5)).collect();
+    let mut y = vec![0.0f64; len + 1];
+    for t in 0..len {
+        let window = y[t.saturating_sub(order - 1)..=t].iter().sum::<f64>();
+        let delayed = if t + 1 >= order {
+            u[t + 1 - order]
+        } else {
+            0.0
+        };
+        y[t + 1] = 0.3 * y[t] + 0.05 * y[t] * window + 1.5 * delayed * u[t] + 0.1;
+    }
+    Sequence {
+        inputs: u.iter().map(|&u| vec![u as f32]).collect(),
+        targets: y[1..].iter().map(|&y| vec![y as f32]).collect(),
+    }
+}
+
+/// Random bits as inputs, the target of each step is the bit `delay` steps
+/// earlier, 0 before the first one.
+pub fn delayed_recall(len: usize, delay: usize, seed: u64) -> Sequence {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let bits: Vec<f32> = (0..len)
+        .map(|_| if rng.random_bool(0.5) { 1.0 } else { 0.0 })
+        .collect();
+    Sequence {
+        inputs: bits.iter().map(|&b| vec![b]).collect(),
+        targets: (0..len)
+            .map(|t| vec![if t >= delay { bits[t - delay] } else { 0.0 }])
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Reservoir, SpikeFilter, delayed_recall, evaluate};
+    use crate::{CurrentEncoder, Model, WeightDist};
+
+    /// The whole loop on a benchmark: a trained readout recalls the previous
+    /// bit better than predicting the mean of the training targets.
+    #[test]
+    fn recalls_better_than_the_mean() {
+        let weights = WeightDist::Uniform {
+            low: -0.3,
+            high: 0.5,
+        };
+        let model = Model::erdos_renyi(120, 0.08, weights, 4);
+        let encoder = CurrentEncoder::new(0..30, 1.2);
+        let filter = SpikeFilter::new(0..120, 10.0);
+        let mut reservoir = Reservoir::new(model, encoder, filter, 5);
+
+        let (train, test) = delayed_recall(900, 1, 9).split_at(600);
+        let readout = reservoir
+            .train(std::slice::from_ref(&train), 20, 1e-2)
+            .unwrap();
+        let scored = reservoir.evaluate(&readout, std::slice::from_ref(&test), 20);
+
+        let count = (train.len() - 20) as f32;
+        let mean = train.targets[20..].iter().map(|t| t[0]).sum::<f32>() / count;
+        let baseline = evaluate(&vec![vec![mean]; test.len() - 20], &test.targets[20..]);
+        assert_eq!(scored.steps, baseline.steps);
+        assert!(baseline.nrmse > 0.9);
+        assert!(scored.nrmse < 0.8 * baseline.nrmse, "{scored:?}");
+    }
+}
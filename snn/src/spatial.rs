@@ -0,0 +1,184 @@
+//! Neurons placed in space: distance-dependent weights, connection
+//! probabilities and conduction delays.
+//!
+//! Coordinates are 3D, 2D networks keep z at 0.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{DEFAULT_SYNAPSE_WEIGHT, Model, NeuronModel, pending_ring};
+
+pub fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    let dx = a[0] - b[0];
+    let dy = a[1] - b[1];
+    let dz = a[2] - b[2];
+    (dx * dx + dy * dy + dz * dz).sqrt()
+}
+
+/// Weight of a synapse as a function of its length.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DistanceWeight {
+    Constant(f32),
+    /// `peak * exp(-d² / 2σ²)`
+    Gaussian {
+        peak: f32,
+        sigma: f32,
+    },
+    /// `peak * exp(-d / length)`
+    Exponential {
+        peak: f32,
+        length: f32,
+    },
+}
+
+impl Default for DistanceWeight {
+    fn default() -> Self {
+        Self::Constant(DEFAULT_SYNAPSE_WEIGHT)
+    }
+}
+
+impl DistanceWeight {
+    pub fn weight(&self, distance: f32) -> f32 {
+        match *self {
+            Self::Constant(w) => w,
+            Self::Gaussian { peak, sigma } => {
+                peak * (-distance * distance / (2.0 * sigma * sigma)).exp()
+            }
+            Self::Exponential { peak, length } => peak * (-distance / length).exp(),
+        }
+    }
+}
+
+/// Probability that two neurons are connected, as a function of their distance.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DistanceProfile {
+    /// Every pair up to the radius
+    Within(f32),
+    /// `p_max * exp(-d² / 2σ²)`
+    Gaussian { p_max: f64, sigma: f32 },
+    /// `p_max * exp(-d / length)`
+    Exponential { p_max: f64, length: f32 },
+}
+
+impl DistanceProfile {
+    pub fn probability(&self, distance: f32) -> f64 {
+        let d = distance as f64;
+        let p = match *self {
+            Self::Within(radius) => {
+                if distance <= radius {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Self::Gaussian { p_max, sigma } => {
+                let sigma = sigma as f64;
+                p_max * (-d * d / (2.0 * sigma * sigma)).exp()
+            }
+            Self::Exponential { p_max, length } => p_max * (-d / length as f64).exp(),
+        };
+        p.clamp(0.0, 1.0)
+    }
+}
+
+/// Conduction delay of a synapse proportional to its length.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DistanceDelay {
+    /// Distance a spike travels per tick
+    pub velocity: f32,
+}
+
+impl DistanceDelay {
+    /// Ticks to cover `distance`, rounded up and at least 1.
+    pub fn delay(&self, distance: f32) -> u16 {
+        let ticks = (distance / self.velocity).ceil();
+        if ticks.is_nan() {
+            return 1;
+        }
+        ticks.clamp(1.0, u16::MAX as f32) as u16
+    }
+}
+
+impl<N: NeuronModel> Model<N> {
+    /// Place every neuron.
+    ///
+    /// Panics if the number of positions differs from the neuron count.
+    pub fn set_coordinates(&mut self, coordinates: Vec<[f32; 3]>) {
+        if let Err(e) = self.check_coordinates(&coordinates) {
+            panic!("{e}");
+        }
+        self.coordinates = Some(coordinates);
+    }
+
+    /// Place every neuron in the plane.
+    ///
+    /// Panics if the number of positions differs from the neuron count.
+    pub fn set_coordinates_2d(&mut self, coordinates: impl IntoIterator<Item = [f32; 2]>) {
+        self.set_coordinates(coordinates.into_iter().map(|[x, y]| [x, y, 0.0]).collect());
+    }
+
+    pub fn clear_coordinates(&mut self) {
+        self.coordinates = None;
+    }
+
+    /// Distance between two neurons, if the model has coordinates.
+    ///
+    /// Panics if either neuron does not exist.
+    pub fn distance(&self, a: u32, b: u32) -> Option<f32> {
+        for neuron in [a, b] {
+            if let Err(e) = self.check_neuron(neuron) {
+                panic!("{e}");
+            }
+        }
+        let coordinates = self.coordinates.as_ref()?;
+        Some(distance(coordinates[a as usize], coordinates[b as usize]))
+    }
+
+    /// Length of each synapse, in CSR order.
+    ///
+    /// Lengths are straight lines between the coordinates; the model does not
+    /// know about periodic boundaries. A synapse that wraps around a periodic
+    /// lattice from [`lattice`](crate::lattice) measures the whole way across
+    /// instead of one spacing.
+    pub fn synapse_lengths(&self) -> Option<Vec<f32>> {
+        let coordinates = self.coordinates.as_ref()?;
+        let mut lengths = Vec::with_capacity(self.topology.receiver.len());
+        for pre in 0..self.neuron_count() {
+            for &post in &self.topology.receiver[self.outgoing(pre as u32)] {
+                lengths.push(distance(coordinates[pre], coordinates[post as usize]));
+            }
+        }
+        Some(lengths)
+    }
+
+    /// Set the delay of every synapse from its length, see
+    /// [`synapse_lengths`](Self::synapse_lengths). On a periodic lattice the
+    /// synapses across the wrap get the delay of their straight length, far
+    /// longer than their neighbours'.
+    /// Spikes already in flight arrive when they were due, or after the new
+    /// delay of their synapse if that is sooner.
+    ///
+    /// Panics if the model has no coordinates.
+    pub fn set_delays_by_distance(&mut self, delays: DistanceDelay) {
+        let lengths = self
+            .synapse_lengths()
+            .expect("delays by distance need neuron coordinates");
+        for (delay, length) in self.topology_mut().delay.iter_mut().zip(lengths) {
+            *delay = delays.delay(length);
+        }
+
+        // Re-slot pending deliveries for the new ring length. No spike waits
+        // longer than its delay, so the ring stays one slot past the longest.
+        let time = self.state.time;
+        let old_len = self.state.pending.len() as u64;
+        let mut pending = pending_ring(&self.topology.delay);
+        let new_len = pending.len() as u64;
+        for wait in 1..old_len {
+            let slot = ((time + wait) % old_len) as usize;
+            for j in std::mem::take(&mut self.state.pending[slot]) {
+                let wait = wait.min(self.topology.delay[j as usize] as u64);
+                pending[((time + wait) % new_len) as usize].push(j);
+            }
+        }
+        self.state.pending = pending;
+    }
+}
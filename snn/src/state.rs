@@ -0,0 +1,124 @@
+//! The two halves of a [`Model`](crate::Model): the synapses, which trials of
+//! the same network share, and the dynamic state of one trial.
+//!
+//! A model holds its topology behind an [`Arc`](std::sync::Arc). Models made
+//! with [`fork`](crate::Model::fork) or
+//! [`from_topology`](crate::Model::from_topology) share it until one of them
+//! changes a weight or delay, which copies it for that model.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{DEFAULT_SYNAPSE_DELAY, NeuronModel};
+
+/// Synapses in CSR layout.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Topology {
+    /// start/end in "receiver" etc for neuron i is out_offset[i]..out_offset[i+1]
+    pub(crate) out_offset: Vec<u32>,
+    /// Receiver Neuron
+    pub(crate) receiver: Vec<u32>,
+    /// Synapse weight
+    pub(crate) weight: Vec<f32>,
+    /// Axonal delay in ticks, always >= 1
+    pub(crate) delay: Vec<u16>,
+}
+
+impl Topology {
+    pub fn neuron_count(&self) -> usize {
+        self.out_offset.len() - 1
+    }
+
+    pub fn synapse_count(&self) -> usize {
+        self.receiver.len()
+    }
+
+    /// Synapse indices leaving `neuron`.
+    pub fn outgoing(&self, neuron: u32) -> std::ops::Range<usize> {
+        let i = neuron as usize;
+        self.out_offset[i] as usize..self.out_offset[i + 1] as usize
+    }
+
+    /// Postsynaptic neuron of each synapse.
+    pub fn receivers(&self) -> &[u32] {
+        &self.receiver
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.weight
+    }
+
+    pub fn delays(&self) -> &[u16] {
+        &self.delay
+    }
+
+    /// Longest synapse delay, the default delay without synapses.
+    pub fn max_delay(&self) -> u16 {
+        self.delay
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or(DEFAULT_SYNAPSE_DELAY)
+    }
+}
+
+/// Everything a tick changes, for one trial.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SimState<N> {
+    /// Neuron dynamics, leaky integrate-and-fire unless chosen otherwise
+    pub(crate) neurons: N,
+    /// Spiked flag for each neuron, so we can render neuron charges
+    pub(crate) spiked: Vec<bool>,
+    /// External current for the next tick, cleared after each tick
+    pub(crate) input: Vec<f32>,
+    /// Number of spikes currently in flight on each synapse
+    pub(crate) in_flight: Vec<u32>,
+    /// Ring buffer of pending deliveries, indexed by arrival tick modulo its length.
+    /// Each slot holds the synapses that deliver on that tick.
+    pub(crate) pending: Vec<Vec<u32>>,
+    /// Number of ticks simulated so far
+    pub(crate) time: u64,
+}
+
+impl<N: NeuronModel> SimState<N> {
+    /// A network at rest at tick 0 with nothing in flight.
+    pub fn new(topology: &Topology, neurons: N) -> Self {
+        assert_eq!(
+            neurons.len(),
+            topology.neuron_count(),
+            "need one neuron per node of the topology"
+        );
+        Self {
+            spiked: vec![false; neurons.len()],
+            input: vec![0.0; neurons.len()],
+            neurons,
+            in_flight: vec![0; topology.synapse_count()],
+            pending: vec![Vec::new(); topology.max_delay() as usize + 1],
+            time: 0,
+        }
+    }
+
+    pub fn neurons(&self) -> &N {
+        &self.neurons
+    }
+
+    /// Whether each neuron spiked on the latest tick.
+    pub fn spiked(&self) -> &[bool] {
+        &self.spiked
+    }
+
+    /// Number of spikes in flight for each synapse.
+    pub fn in_flight(&self) -> &[u32] {
+        &self.in_flight
+    }
+
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// Whether the state fits `topology`.
+    pub(crate) fn fits(&self, topology: &Topology) -> bool {
+        self.neurons.len() == topology.neuron_count()
+            && self.in_flight.len() == topology.synapse_count()
+            && self.pending.len() == topology.max_delay() as usize + 1
+    }
+}
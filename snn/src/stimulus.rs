@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Population};
+
+/// Shape of a stimulus current over time. Times are in ticks, relative to the
+/// start of the stimulus.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Waveform {
+    /// Constant current
+    Step { amplitude: f32 },
+    /// Linear change from `from` to `to` over the duration of the stimulus
+    Ramp { from: f32, to: f32 },
+    /// `amplitude` for the first `width` ticks of every `period`, 0 otherwise
+    PulseTrain {
+        amplitude: f32,
+        period: u64,
+        width: u64,
+    },
+    /// offset + amplitude * sin(2π t / period + phase)
+    Sine {
+        amplitude: f32,
+        period: f32,
+        phase: f32,
+        offset: f32,
+    },
+}
+
+impl Waveform {
+    /// [`Error::InvalidStimulus`] for a sine without a positive, finite period,
+    /// or a pulse train without a period or with pulses longer than it.
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        match *self {
+            Self::Sine { period, .. } if !(period.is_finite() && period > 0.0) => Err(
+                Error::InvalidStimulus("sine period must be positive and finite"),
+            ),
+            Self::PulseTrain { period: 0, .. } => Err(Error::InvalidStimulus(
+                "pulse train period must be at least 1 tick",
+            )),
+            Self::PulseTrain { period, width, .. } if width > period => Err(
+                Error::InvalidStimulus("pulse width must not exceed the period"),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A current applied to a set of neurons for a limited time.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Stimulus {
+    pub targets: Vec<u32>,
+    pub waveform: Waveform,
+    /// First tick the current is applied on
+    pub start: u64,
+    /// Number of ticks the current is applied for
+    pub duration: u64,
+}
+
+impl Stimulus {
+    pub fn new(
+        targets: impl IntoIterator<Item = u32>,
+        waveform: Waveform,
+        start: u64,
+        duration: u64,
+    ) -> Self {
+        Self {
+            targets: targets.into_iter().collect(),
+            waveform,
+            start,
+            duration,
+        }
+    }
+
+    /// A stimulus on every neuron of a population.
+    pub fn population(
+        population: &Population,
+        waveform: Waveform,
+        start: u64,
+        duration: u64,
+    ) -> Self {
+        Self::new(population.neurons(), waveform, start, duration)
+    }
+
+    /// First tick after the stimulus.
+    pub fn end(&self) -> u64 {
+        self.start.saturating_add(self.duration)
+    }
+
+    pub fn is_active(&self, tick: u64) -> bool {
+        tick >= self.start && tick < self.end()
+    }
+
+    /// Current on `tick`, 0 outside the stimulus.
+    pub fn current_at(&self, tick: u64) -> f32 {
+        if !self.is_active(tick) {
+            return 0.0;
+        }
+        let t = tick - self.start;
+        match self.waveform {
+            Waveform::Step { amplitude } => amplitude,
+            Waveform::Ramp { from, to } => {
+                let frac = if self.duration > 1 {
+                    t as f32 / (self.duration - 1) as f32
+                } else {
+                    0.0
+                };
+                from + (to - from) * frac
+            }
+            Waveform::PulseTrain {
+                amplitude,
+                period,
+                width,
+            } => {
+                if period > 0 && t % period < width {
+                    amplitude
+                } else {
+                    0.0
+                }
+            }
+            Waveform::Sine {
+                amplitude,
+                period,
+                phase,
+                offset,
+            } => offset + amplitude * (std::f32::consts::TAU * t as f32 / period + phase).sin(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Stimulus, Waveform};
+    use crate::validate::Violation;
+    use crate::{Error, Model, WeightDist};
+
+    /// Pulse trains without a period or with pulses longer than it are
+    /// refused, and reported if they get in anyway.
+    #[test]
+    fn rejects_bad_pulse_trains() {
+        let mut model = Model::erdos_renyi(4, 0.5, WeightDist::Constant(0.1), 1);
+        let pulses = |period, width| {
+            Stimulus::new(
+                [0, 1],
+                Waveform::PulseTrain {
+                    amplitude: 1.0,
+                    period,
+                    width,
+                },
+                0,
+                10,
+            )
+        };
+        for (period, width) in [(0, 0), (0, 1), (3, 4)] {
+            assert!(matches!(
+                model.try_add_stimulus(pulses(period, width)),
+                Err(Error::InvalidStimulus(_))
+            ));
+        }
+        model.try_add_stimulus(pulses(3, 3)).unwrap();
+        model.try_add_stimulus(pulses(1, 0)).unwrap();
+
+        model.stimuli.push(pulses(2, 5));
+        assert!(matches!(
+            model.validate().violations(),
+            [Violation::InvalidStimulus { stimulus: 2, .. }]
+        ));
+    }
+}
@@ -0,0 +1,463 @@
+//! Editing the network of a live [`Model`].
+//!
+//! Neurons and synapses carry keys that stay the same for as long as they
+//! exist, while their indices shift when something before them is removed.
+//! Appending a neuron takes effect at once. Removals and new synapses are
+//! logged and folded into the CSR arrays in a single pass by
+//! [`compact`](Model::compact), which [`tick`](Model::tick) runs first
+//! whenever edits are waiting, so a batch of edits costs one rebuild. Until
+//! then indices and the index-based accessors show the network without the
+//! logged edits.
+//!
+//! Compaction keeps the dynamic state of everything it doesn't remove:
+//! potentials, spikes in flight, learning traces, monitors and stimuli.
+//! Spikes in flight on a removed synapse are dropped.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::neuron::retain_mask;
+use crate::{Error, Model, NeuronKind, NeuronModel, Topology};
+
+/// New index of a removed neuron, also left in the slots of state monitors
+pub const REMOVED: u32 = u32::MAX;
+/// Old index of a synapse added since the last compaction
+pub(crate) const ADDED: u32 = u32::MAX;
+
+/// Identity of a neuron that survives compaction, unlike its index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct NeuronKey(pub u64);
+
+/// Identity of a synapse that survives compaction, unlike its index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct SynapseKey(pub u64);
+
+/// Keys and pending edits of a model, created by its first edit.
+/// Until then the key of every neuron and synapse is its index.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Structure {
+    /// Key of each neuron, ascending since new neurons go last
+    pub(crate) neuron_keys: Vec<u64>,
+    /// Key of each synapse, in CSR order
+    pub(crate) synapse_keys: Vec<u64>,
+    next_neuron: u64,
+    next_synapse: u64,
+    /// Index of each synapse key, derived from `synapse_keys`
+    #[serde(skip)]
+    synapse_index: HashMap<u64, u32>,
+    /// Not saved, a model cannot be saved while edits are waiting
+    #[serde(skip)]
+    log: EditLog,
+}
+
+/// Edits waiting for the next compaction.
+#[derive(Clone, Debug, Default)]
+struct EditLog {
+    /// Neurons to drop, missing entries at the end are kept
+    removed_neurons: Vec<bool>,
+    /// Synapses to drop, missing entries at the end are kept
+    removed_synapses: Vec<bool>,
+    added: Vec<NewSynapse>,
+    /// Whether the CSR arrays are out of date
+    dirty: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct NewSynapse {
+    key: u64,
+    pre: u32,
+    post: u32,
+    weight: f32,
+    delay: u16,
+}
+
+impl Structure {
+    /// Keys equal to the indices of `neurons` neurons and `synapses` synapses.
+    fn identity(neurons: usize, synapses: usize) -> Self {
+        let mut structure = Self {
+            neuron_keys: (0..neurons as u64).collect(),
+            synapse_keys: (0..synapses as u64).collect(),
+            next_neuron: neurons as u64,
+            next_synapse: synapses as u64,
+            ..Self::default()
+        };
+        structure.rebuild_index();
+        structure
+    }
+
+    /// Recompute the lookup from synapse key to index, after compaction or loading.
+    pub(crate) fn rebuild_index(&mut self) {
+        self.synapse_index = self
+            .synapse_keys
+            .iter()
+            .enumerate()
+            .map(|(j, &key)| (key, j as u32))
+            .collect();
+    }
+}
+
+fn flagged(mask: &[bool], i: usize) -> bool {
+    mask.get(i).copied().unwrap_or(false)
+}
+
+fn flag(mask: &mut Vec<bool>, i: usize) {
+    if mask.len() <= i {
+        mask.resize(i + 1, false);
+    }
+    mask[i] = true;
+}
+
+impl<N: NeuronModel> Model<N> {
+    fn structure_mut(&mut self) -> &mut Structure {
+        let (n, e) = (self.neuron_count(), self.synapse_count());
+        self.structure
+            .get_or_insert_with(|| Structure::identity(n, e))
+    }
+
+    /// Stable key of a neuron, it survives compaction.
+    ///
+    /// Panics if the neuron does not exist.
+    pub fn neuron_key(&self, neuron: u32) -> NeuronKey {
+        if let Err(e) = self.check_neuron(neuron) {
+            panic!("{e}");
+        }
+        match &self.structure {
+            Some(structure) => NeuronKey(structure.neuron_keys[neuron as usize]),
+            None => NeuronKey(neuron as u64),
+        }
+    }
+
+    /// Current index of a neuron, `None` once it is removed.
+    pub fn neuron_index(&self, key: NeuronKey) -> Option<u32> {
+        match &self.structure {
+            Some(structure) => structure
+                .neuron_keys
+                .binary_search(&key.0)
+                .ok()
+                .filter(|&i| !flagged(&structure.log.removed_neurons, i))
+                .map(|i| i as u32),
+            None => (key.0 < self.neuron_count() as u64).then_some(key.0 as u32),
+        }
+    }
+
+    /// Stable key of a synapse, it survives compaction.
+    ///
+    /// Panics if the synapse does not exist.
+    pub fn synapse_key(&self, synapse: u32) -> SynapseKey {
+        if let Err(e) = self.check_synapse(synapse) {
+            panic!("{e}");
+        }
+        match &self.structure {
+            Some(structure) => SynapseKey(structure.synapse_keys[synapse as usize]),
+            None => SynapseKey(synapse as u64),
+        }
+    }
+
+    /// Current index of a synapse, `None` once it or one of its neurons is
+    /// removed, and for synapses inserted since the last compaction.
+    pub fn synapse_index(&self, key: SynapseKey) -> Option<u32> {
+        let Some(structure) = &self.structure else {
+            return (key.0 < self.synapse_count() as u64).then_some(key.0 as u32);
+        };
+        let j = *structure.synapse_index.get(&key.0)?;
+        let log = &structure.log;
+        let removed = flagged(&log.removed_synapses, j as usize)
+            || flagged(&log.removed_neurons, self.presynaptic(j))
+            || flagged(
+                &log.removed_neurons,
+                self.topology.receiver[j as usize] as usize,
+            );
+        (!removed).then_some(j)
+    }
+
+    /// Whether edits are waiting for [`compact`](Self::compact).
+    pub fn has_pending_edits(&self) -> bool {
+        self.structure.as_ref().is_some_and(|s| s.log.dirty)
+    }
+
+    /// Append a neuron at rest. It takes the next index right away, is
+    /// excitatory, unbiased and, if the model has coordinates, at the origin.
+    pub fn insert_neuron(&mut self, params: N::Params) -> NeuronKey {
+        let structure = self.structure_mut();
+        let key = structure.next_neuron;
+        structure.next_neuron += 1;
+        structure.neuron_keys.push(key);
+
+        self.state.neurons.push(params);
+        self.state.spiked.push(false);
+        self.state.input.push(0.0);
+        self.bias.push(0.0);
+        self.kinds.push(NeuronKind::Excitatory);
+        if let Some(coordinates) = &mut self.coordinates {
+            coordinates.push([0.0; 3]);
+        }
+        if let Some(plasticity) = &mut self.plasticity {
+            plasticity.push_neuron();
+        }
+        let out_offset = &mut self.topology_mut().out_offset;
+        out_offset.push(*out_offset.last().unwrap_or(&0));
+        NeuronKey(key)
+    }
+
+    /// Remove a neuron and all of its synapses at the next compaction.
+    pub fn remove_neuron(&mut self, key: NeuronKey) -> Result<(), Error> {
+        let i = self.neuron_index(key).ok_or(Error::UnknownNeuron(key))?;
+        let log = &mut self.structure_mut().log;
+        flag(&mut log.removed_neurons, i as usize);
+        log.dirty = true;
+        Ok(())
+    }
+
+    /// Add a synapse at the next compaction, after the other synapses of `pre`.
+    /// Under Dale's law only the magnitude of `weight` counts.
+    pub fn insert_synapse(
+        &mut self,
+        pre: NeuronKey,
+        post: NeuronKey,
+        weight: f32,
+        delay: u16,
+    ) -> Result<SynapseKey, Error> {
+        if delay == 0 {
+            return Err(Error::ZeroDelay);
+        }
+        let pre = self.neuron_index(pre).ok_or(Error::UnknownNeuron(pre))?;
+        let post = self.neuron_index(post).ok_or(Error::UnknownNeuron(post))?;
+        let structure = self.structure_mut();
+        let key = structure.next_synapse;
+        structure.next_synapse += 1;
+        structure.log.added.push(NewSynapse {
+            key,
+            pre,
+            post,
+            weight,
+            delay,
+        });
+        structure.log.dirty = true;
+        Ok(SynapseKey(key))
+    }
+
+    /// Remove a synapse at the next compaction.
+    pub fn remove_synapse(&mut self, key: SynapseKey) -> Result<(), Error> {
+        if let Some(j) = self.synapse_index(key) {
+            let log = &mut self.structure_mut().log;
+            flag(&mut log.removed_synapses, j as usize);
+            log.dirty = true;
+            return Ok(());
+        }
+        let log = &mut self
+            .structure
+            .as_mut()
+            .ok_or(Error::UnknownSynapse(key))?
+            .log;
+        let k = log
+            .added
+            .iter()
+            .position(|s| s.key == key.0)
+            .ok_or(Error::UnknownSynapse(key))?;
+        log.added.remove(k);
+        Ok(())
+    }
+
+    /// Point a synapse at another postsynaptic neuron, keeping its key,
+    /// weight and delay. Takes effect at once, spikes already in flight on
+    /// the synapse arrive at the new neuron.
+    pub fn rewire_synapse(&mut self, key: SynapseKey, post: NeuronKey) -> Result<(), Error> {
+        let post = self.neuron_index(post).ok_or(Error::UnknownNeuron(post))?;
+        if let Some(j) = self.synapse_index(key) {
+            self.topology_mut().receiver[j as usize] = post;
+            // The reverse index of learning is rebuilt on compaction
+            self.structure_mut().log.dirty = true;
+            return Ok(());
+        }
+        let synapse = self
+            .structure
+            .as_mut()
+            .and_then(|s| s.log.added.iter_mut().find(|s| s.key == key.0))
+            .ok_or(Error::UnknownSynapse(key))?;
+        synapse.post = post;
+        Ok(())
+    }
+
+    /// Fold the logged edits into the CSR arrays. Neurons and synapses after
+    /// a removed one move to lower indices, their keys stay.
+    pub fn compact(&mut self) {
+        let Some(structure) = &mut self.structure else {
+            return;
+        };
+        if !structure.log.dirty {
+            return;
+        }
+        let log = std::mem::take(&mut structure.log);
+        let n = self.state.neurons.len();
+
+        // New index of every neuron, and of the neurons before each one
+        let keep: Vec<bool> = (0..n).map(|i| !flagged(&log.removed_neurons, i)).collect();
+        let mut neuron_map = vec![REMOVED; n];
+        let mut kept_before = Vec::with_capacity(n + 1);
+        let mut kept = 0;
+        for i in 0..n {
+            kept_before.push(kept);
+            if keep[i] {
+                neuron_map[i] = kept;
+                kept += 1;
+            }
+        }
+        kept_before.push(kept);
+
+        // New synapses go after the old ones of their presynaptic neuron,
+        // in the order they were inserted
+        let mut added = log.added;
+        added.sort_by_key(|s| s.pre);
+
+        let old = &self.topology;
+        let capacity = old.receiver.len() + added.len();
+        let mut topology = Topology {
+            out_offset: Vec::with_capacity(kept as usize + 1),
+            receiver: Vec::with_capacity(capacity),
+            weight: Vec::with_capacity(capacity),
+            delay: Vec::with_capacity(capacity),
+        };
+        // Old index of each new synapse, ADDED for inserted ones
+        let mut sources = Vec::with_capacity(capacity);
+        let mut keys = Vec::with_capacity(capacity);
+        topology.out_offset.push(0);
+        let mut next = 0;
+        for (pre, &kept) in keep.iter().enumerate() {
+            let first = next;
+            while next < added.len() && added[next].pre as usize == pre {
+                next += 1;
+            }
+            if !kept {
+                continue;
+            }
+            for j in old.outgoing(pre as u32) {
+                let post = neuron_map[old.receiver[j] as usize];
+                if post == REMOVED || flagged(&log.removed_synapses, j) {
+                    continue;
+                }
+                topology.receiver.push(post);
+                topology.weight.push(old.weight[j]);
+                topology.delay.push(old.delay[j]);
+                sources.push(j as u32);
+                keys.push(structure.synapse_keys[j]);
+            }
+            for synapse in &added[first..next] {
+                let post = neuron_map[synapse.post as usize];
+                if post == REMOVED {
+                    continue;
+                }
+                let weight = if self.dale {
+                    self.kinds[pre].sign() * synapse.weight.abs()
+                } else {
+                    synapse.weight
+                };
+                topology.receiver.push(post);
+                topology.weight.push(weight);
+                topology.delay.push(synapse.delay);
+                sources.push(ADDED);
+                keys.push(synapse.key);
+            }
+            topology.out_offset.push(topology.receiver.len() as u32);
+        }
+
+        let mut synapse_map = vec![REMOVED; old.receiver.len()];
+        for (j, &source) in sources.iter().enumerate() {
+            if source != ADDED {
+                synapse_map[source as usize] = j as u32;
+            }
+        }
+
+        // Spikes in flight, re-slotted for the new longest delay. A spike
+        // never waits longer than its delay, the clamp keeps a ring from an
+        // older state that broke this from wrapping around.
+        let now = self.state.time;
+        let old_len = self.state.pending.len() as u64;
+        let ring_len = topology.max_delay() as u64 + 1;
+        let mut pending = vec![Vec::new(); ring_len as usize];
+        for wait in 1..old_len {
+            let slot = ((now + wait) % old_len) as usize;
+            for &j in &self.state.pending[slot] {
+                let j = synapse_map[j as usize];
+                if j != REMOVED {
+                    let wait = wait.min(topology.delay[j as usize] as u64);
+                    pending[((now + wait) % ring_len) as usize].push(j);
+                }
+            }
+        }
+        self.state.pending = pending;
+        self.state.in_flight = sources
+            .iter()
+            .map(|&j| self.state.in_flight.get(j as usize).copied().unwrap_or(0))
+            .collect();
+
+        // Per-neuron state and everything that refers to neurons
+        self.state.neurons.retain(&keep);
+        retain_mask(&mut self.state.spiked, &keep);
+        retain_mask(&mut self.state.input, &keep);
+        retain_mask(&mut self.bias, &keep);
+        retain_mask(&mut self.kinds, &keep);
+        if let Some(coordinates) = &mut self.coordinates {
+            retain_mask(coordinates, &keep);
+        }
+        for population in &mut self.populations {
+            let range = &population.range;
+            population.range = kept_before[range.start as usize]..kept_before[range.end as usize];
+        }
+        for stimulus in &mut self.stimuli {
+            stimulus.targets.retain_mut(|target| {
+                *target = neuron_map.get(*target as usize).copied().unwrap_or(REMOVED);
+                *target != REMOVED
+            });
+        }
+        self.monitors.remap(&neuron_map);
+        if let Some(plasticity) = &mut self.plasticity {
+            plasticity.restructure(&keep, &sources, &topology.out_offset, &topology.receiver);
+        }
+
+        retain_mask(&mut structure.neuron_keys, &keep);
+        structure.synapse_keys = keys;
+        structure.rebuild_index();
+        self.topology = Arc::new(topology);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DistanceDelay, ModelBuilder, NeuronId, NeuronParams};
+
+    /// Spikes in flight arrive on the same tick whether or not a compaction
+    /// follows a change to shorter delays.
+    #[test]
+    fn compact_keeps_arrivals_after_shorter_delays() {
+        let build = || {
+            let mut builder = ModelBuilder::new();
+            for x in [0.0, 4.0, 8.0, 30.0] {
+                builder.add_neuron_at(NeuronParams::default(), [x, 0.0, 0.0]);
+            }
+            builder.connect_delayed(NeuronId(0), NeuronId(1), 2.0, 8);
+            builder.connect_delayed(NeuronId(1), NeuronId(2), 2.0, 8);
+            let mut model = builder.build();
+            model.set_charge(0, 2.0);
+            model.tick();
+            model.tick();
+            model.set_delays_by_distance(DistanceDelay { velocity: 1.0 });
+            model
+        };
+        let mut reference = build();
+        let mut edited = build();
+        let isolated = edited.neuron_key(3);
+        edited.remove_neuron(isolated).unwrap();
+
+        let mut spikes = 0;
+        for _ in 0..20 {
+            reference.tick();
+            edited.tick();
+            assert_eq!(&reference.neuron_vs()[..3], edited.neuron_vs());
+            assert_eq!(&reference.spiked()[..3], edited.spiked());
+            spikes += edited.spiked().iter().filter(|&&s| s).count();
+        }
+        assert_eq!(spikes, 2);
+    }
+}
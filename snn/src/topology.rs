@@ -0,0 +1,332 @@
+//! Seeded random network generators.
+//!
+//! All generators build LIF neurons with default parameters and synapses with
+//! the default delay, drawing each synapse weight from a [`WeightDist`].
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{LifParams, Model, ModelBuilder, NeuronId, NeuronKind};
+
+/// Distribution synapse weights are drawn from.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WeightDist {
+    Constant(f32),
+    /// Uniform in `[low, high)`
+    Uniform {
+        low: f32,
+        high: f32,
+    },
+    Normal {
+        mean: f32,
+        std: f32,
+    },
+    /// `exp(x)` for `x` normal with mean `mu` and standard deviation `sigma`
+    LogNormal {
+        mu: f32,
+        sigma: f32,
+    },
+}
+
+impl WeightDist {
+    pub fn sample(&self, rng: &mut impl Rng) -> f32 {
+        match *self {
+            Self::Constant(w) => w,
+            Self::Uniform { low, high } => low + (high - low) * rng.random::<f32>(),
+            Self::Normal { mean, std } => mean + std * standard_normal(rng),
+            Self::LogNormal { mu, sigma } => (mu + sigma * standard_normal(rng)).exp(),
+        }
+    }
+}
+
+/// Box–Muller transform
+fn standard_normal(rng: &mut impl Rng) -> f32 {
+    // 1 - u is in (0, 1], keeping the log finite
+    let u1 = 1.0 - rng.random::<f32>();
+    let u2 = rng.random::<f32>();
+    (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+}
+
+/// Random network of excitatory and inhibitory neurons, see [`Model::balanced`].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Balanced {
+    pub excitatory: usize,
+    pub inhibitory: usize,
+    /// Connection probability of every ordered pair
+    pub p: f64,
+    pub excitatory_weight: WeightDist,
+    /// Magnitude of inhibitory weights, they are negated
+    pub inhibitory_weight: WeightDist,
+    pub dale: bool,
+}
+
+impl Default for Balanced {
+    fn default() -> Self {
+        // 80/20 with inhibition 4 times as strong balances the mean input
+        Self {
+            excitatory: 800,
+            inhibitory: 200,
+            p: 0.1,
+            excitatory_weight: WeightDist::Constant(0.1),
+            inhibitory_weight: WeightDist::Constant(0.4),
+            dale: true,
+        }
+    }
+}
+
+impl Model {
+    /// Erdős–Rényi network with the excitatory neurons first, then the
+    /// inhibitory ones. Inhibitory neurons only make negative synapses.
+    pub fn balanced(config: Balanced, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let p = config.p.clamp(0.0, 1.0);
+        let n = config.excitatory + config.inhibitory;
+        let mut builder = ModelBuilder::new();
+        builder.add_neurons_of_kind(
+            config.excitatory,
+            LifParams::default(),
+            NeuronKind::Excitatory,
+        );
+        builder.add_neurons_of_kind(
+            config.inhibitory,
+            LifParams::default(),
+            NeuronKind::Inhibitory,
+        );
+        for pre in 0..n {
+            let (weights, sign) = if pre < config.excitatory {
+                (config.excitatory_weight, 1.0)
+            } else {
+                (config.inhibitory_weight, -1.0)
+            };
+            for post in 0..n {
+                if pre != post && rng.random_bool(p) {
+                    let w = sign * weights.sample(&mut rng).abs();
+                    builder.connect(NeuronId(pre as u32), NeuronId(post as u32), w);
+                }
+            }
+        }
+        if config.dale {
+            builder.enforce_dale();
+        }
+        builder.build()
+    }
+
+    /// Directed Erdős–Rényi graph: every ordered pair of distinct neurons is
+    /// connected with probability `p`.
+    pub fn erdos_renyi(neurons: usize, p: f64, weights: WeightDist, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let p = p.clamp(0.0, 1.0);
+        let mut builder = ModelBuilder::new();
+        builder.add_neurons(neurons, LifParams::default());
+        for pre in 0..neurons as u32 {
+            for post in 0..neurons as u32 {
+                if pre != post && rng.random_bool(p) {
+                    let w = weights.sample(&mut rng);
+                    builder.connect(NeuronId(pre), NeuronId(post), w);
+                }
+            }
+        }
+        builder.build()
+    }
+
+    /// Watts–Strogatz small world: a ring where each neuron connects to its
+    /// `k` nearest neighbours, `k / 2` on each side, after which every synapse
+    /// is rewired to a random receiver with probability `beta`.
+    /// Rewiring avoids self-loops and duplicate synapses.
+    ///
+    /// Panics if `k` is odd or not smaller than `neurons`.
+    pub fn watts_strogatz(
+        neurons: usize,
+        k: usize,
+        beta: f64,
+        weights: WeightDist,
+        seed: u64,
+    ) -> Self {
+        assert!(k.is_multiple_of(2), "k must be even, got {k}");
+        assert!(
+            k < neurons.max(1),
+            "k = {k} needs more than {neurons} neurons"
+        );
+        let mut rng = StdRng::seed_from_u64(seed);
+        let beta = beta.clamp(0.0, 1.0);
+        let mut builder = ModelBuilder::with_capacity(neurons, neurons * k);
+        builder.add_neurons(neurons, LifParams::default());
+        let mut targets = Vec::with_capacity(k);
+        for pre in 0..neurons {
+            targets.clear();
+            for offset in 1..=k / 2 {
+                targets.push((pre + offset) % neurons);
+                targets.push((pre + neurons - offset) % neurons);
+            }
+            for slot in 0..targets.len() {
+                // A neuron with every other neuron as target has nothing to rewire to
+                if targets.len() + 1 < neurons && rng.random_bool(beta) {
+                    loop {
+                        let post = rng.random_range(0..neurons);
+                        if post != pre && !targets.contains(&post) {
+                            targets[slot] = post;
+                            break;
+                        }
+                    }
+                }
+            }
+            for &post in &targets {
+                let w = weights.sample(&mut rng);
+                builder.connect(NeuronId(pre as u32), NeuronId(post as u32), w);
+            }
+        }
+        builder.build()
+    }
+
+    /// Barabási–Albert scale-free graph: starting from `m + 1` fully
+    /// connected neurons, every new neuron links to `m` distinct existing
+    /// ones, chosen with probability proportional to their degree.
+    /// Each link is a pair of synapses of equal weight, one in each direction.
+    ///
+    /// Panics if `m` is 0.
+    pub fn barabasi_albert(neurons: usize, m: usize, weights: WeightDist, seed: u64) -> Self {
+        assert!(m >= 1, "every new neuron needs at least one link");
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut builder = ModelBuilder::with_capacity(neurons, 2 * neurons * m);
+        builder.add_neurons(neurons, LifParams::default());
+
+        // Every link adds both ends, so sampling this uniformly is sampling by degree
+        let mut ends: Vec<u32> = Vec::with_capacity(2 * neurons * m);
+
+        let seed_size = (m + 1).min(neurons) as u32;
+        for a in 0..seed_size {
+            for b in a + 1..seed_size {
+                link(&mut builder, &mut ends, a, b, weights.sample(&mut rng));
+            }
+        }
+
+        let mut chosen = Vec::with_capacity(m);
+        for new in seed_size..neurons as u32 {
+            chosen.clear();
+            while chosen.len() < m {
+                let candidate = ends[rng.random_range(0..ends.len())];
+                if !chosen.contains(&candidate) {
+                    chosen.push(candidate);
+                }
+            }
+            for &old in &chosen {
+                link(&mut builder, &mut ends, new, old, weights.sample(&mut rng));
+            }
+        }
+        builder.build()
+    }
+}
+
+/// Undirected link of `a` and `b` as two synapses of the same weight.
+fn link(builder: &mut ModelBuilder, ends: &mut Vec<u32>, a: u32, b: u32, weight: f32) {
+    builder.connect(NeuronId(a), NeuronId(b), weight);
+    builder.connect(NeuronId(b), NeuronId(a), weight);
+    ends.push(a);
+    ends.push(b);
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::ops::Range;
+
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use crate::{Balanced, Model, ModelBuilder, NeuronId, NeuronParams, WeightDist};
+
+    /// Random network with uniform weights and delays from 1 to `max_delay`
+    /// ticks, for comparing engines on something irregular.
+    pub(crate) fn random_network(
+        neurons: u32,
+        p: f64,
+        weights: Range<f32>,
+        max_delay: u16,
+        seed: u64,
+    ) -> Model {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut builder = ModelBuilder::new();
+        builder.add_neurons(neurons as usize, NeuronParams::default());
+        for pre in 0..neurons {
+            for post in 0..neurons {
+                if pre != post && rng.random_bool(p) {
+                    let weight = rng.random_range(weights.clone());
+                    let delay = rng.random_range(1..=max_delay);
+                    builder.connect_delayed(NeuronId(pre), NeuronId(post), weight, delay);
+                }
+            }
+        }
+        builder.build()
+    }
+
+    /// Panics on a self-loop or a second synapse between the same pair.
+    pub(crate) fn assert_simple(model: &Model) {
+        let topology = model.topology();
+        for pre in 0..model.neuron_count() as u32 {
+            let mut receivers: Vec<u32> = topology.receiver[topology.outgoing(pre)].to_vec();
+            assert!(!receivers.contains(&pre), "neuron {pre} connects to itself");
+            receivers.sort_unstable();
+            let len = receivers.len();
+            receivers.dedup();
+            assert_eq!(receivers.len(), len, "neuron {pre} has duplicate synapses");
+        }
+    }
+
+    fn generate(seed: u64) -> [Model; 4] {
+        let weights = WeightDist::Uniform {
+            low: 0.1,
+            high: 0.5,
+        };
+        [
+            Model::balanced(
+                Balanced {
+                    excitatory: 40,
+                    inhibitory: 10,
+                    p: 0.2,
+                    ..Balanced::default()
+                },
+                seed,
+            ),
+            Model::erdos_renyi(50, 0.2, weights, seed),
+            Model::watts_strogatz(50, 6, 0.3, weights, seed),
+            Model::barabasi_albert(50, 3, weights, seed),
+        ]
+    }
+
+    /// No generator makes self-loops or duplicate synapses.
+    #[test]
+    fn generators_make_simple_graphs() {
+        for model in generate(7) {
+            assert_simple(&model);
+        }
+        // Dense enough that rewiring runs out of free receivers
+        assert_simple(&Model::watts_strogatz(
+            8,
+            6,
+            1.0,
+            WeightDist::Constant(0.1),
+            7,
+        ));
+    }
+
+    /// Equal seeds give identical networks, different seeds do not.
+    #[test]
+    fn seeds_reproduce() {
+        for ((a, b), c) in generate(3).iter().zip(generate(3)).zip(generate(4)) {
+            assert_eq!(a.topology(), b.topology());
+            assert_ne!(a.topology(), c.topology());
+        }
+    }
+
+    /// Without rewiring, Watts–Strogatz is the ring lattice.
+    #[test]
+    fn watts_strogatz_without_rewiring_is_a_ring() {
+        let model = Model::watts_strogatz(20, 4, 0.0, WeightDist::Constant(0.1), 1);
+        let topology = model.topology();
+        for pre in 0..20u32 {
+            let mut receivers = topology.receiver[topology.outgoing(pre)].to_vec();
+            receivers.sort_unstable();
+            let mut ring: Vec<u32> = [1, 2, 18, 19].iter().map(|d| (pre + d) % 20).collect();
+            ring.sort_unstable();
+            assert_eq!(receivers, ring, "neuron {pre}");
+        }
+    }
+}
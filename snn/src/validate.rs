@@ -0,0 +1,370 @@
+//! Integrity checks of a [`Model`].
+//!
+//! Models built through [`ModelBuilder`](crate::ModelBuilder) and the
+//! generators are consistent by construction. [`Model::validate`] is for
+//! models that were loaded or edited from outside: it walks the CSR layout,
+//! the per-neuron and per-synapse arrays, the spikes in flight, the learning
+//! state, stimuli, monitors and neuron parameters, and reports every broken
+//! invariant instead of stopping at the first. It never panics, however
+//! broken the model.
+
+use std::fmt;
+
+use crate::structure::REMOVED;
+use crate::{Error, Model, NeuronModel};
+
+/// One broken invariant.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum Violation {
+    #[error("out_offset has {len} entries, expected {expected}")]
+    OffsetCount { len: usize, expected: usize },
+    #[error("out_offset starts at {0} instead of 0")]
+    OffsetStart(u32),
+    /// `out_offset[neuron] > out_offset[neuron + 1]`
+    #[error("out_offset decreases after neuron {neuron}")]
+    OffsetDecreasing { neuron: u32 },
+    #[error("out_offset ends at {end}, but there are {synapses} synapses")]
+    OffsetEnd { end: u32, synapses: usize },
+    #[error("{array} has {len} entries, expected {expected}")]
+    LengthMismatch {
+        array: &'static str,
+        len: usize,
+        expected: usize,
+    },
+    #[error("synapse {synapse} targets neuron {receiver}, which does not exist")]
+    ReceiverOutOfRange { synapse: u32, receiver: u32 },
+    #[error("synapse {synapse} has a delay of 0 ticks")]
+    ZeroDelay { synapse: u32 },
+    #[error("delivery ring of {ring} slots cannot hold a delay of {delay} ticks")]
+    RingTooShort { ring: usize, delay: u16 },
+    #[error("pending delivery of synapse {synapse}, which does not exist")]
+    PendingOutOfRange { synapse: u32 },
+    #[error("synapse {synapse} counts {in_flight} spike(s) in flight, {pending} are pending")]
+    InFlightMismatch {
+        synapse: u32,
+        in_flight: u32,
+        pending: u32,
+    },
+    #[error("{array}[{index}] is not finite")]
+    NotFinite { array: &'static str, index: usize },
+    #[error("population {name} reaches past the last neuron")]
+    PopulationOutOfRange { name: String },
+    #[error("population {name} starts after it ends")]
+    PopulationReversed { name: String },
+    #[error("stimulus {stimulus} targets neuron {neuron}, which does not exist")]
+    StimulusTargetOutOfRange { stimulus: usize, neuron: u32 },
+    #[error("stimulus {stimulus}: {problem}")]
+    InvalidStimulus {
+        stimulus: usize,
+        problem: &'static str,
+    },
+    #[error("a monitor watches neuron {neuron}, which does not exist")]
+    MonitorOutOfRange { neuron: u32 },
+    #[error("neuron {neuron}: {problem}")]
+    Parameter { neuron: u32, problem: &'static str },
+}
+
+/// Everything [`Model::validate`] found wrong, empty for a sound model.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidationReport {
+    violations: Vec<Violation>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+
+    /// `Ok` for a sound model, [`Error::Invalid`] with the report otherwise.
+    pub fn into_result(self) -> Result<(), Error> {
+        if self.is_valid() {
+            Ok(())
+        } else {
+            Err(Error::Invalid(self))
+        }
+    }
+
+    fn push(&mut self, violation: Violation) {
+        self.violations.push(violation);
+    }
+
+    fn check_len(&mut self, array: &'static str, len: usize, expected: usize) {
+        if len != expected {
+            self.push(Violation::LengthMismatch {
+                array,
+                len,
+                expected,
+            });
+        }
+    }
+
+    fn check_finite(&mut self, array: &'static str, values: &[f32]) {
+        for (index, v) in values.iter().enumerate() {
+            if !v.is_finite() {
+                self.push(Violation::NotFinite { array, index });
+            }
+        }
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} violation(s)", self.violations.len())?;
+        for violation in &self.violations {
+            write!(f, "\n  {violation}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<N: NeuronModel> Model<N> {
+    /// Check the CSR invariants, array lengths and neuron parameters.
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let n = self.neuron_count();
+        let topology = &self.topology;
+        let e = topology.receiver.len();
+
+        // CSR offsets
+        let offsets = &topology.out_offset;
+        report.check_len("out_offset", offsets.len(), n + 1);
+        if let Some(&first) = offsets.first()
+            && first != 0
+        {
+            report.push(Violation::OffsetStart(first));
+        }
+        for (neuron, w) in offsets.windows(2).enumerate() {
+            if w[0] > w[1] {
+                report.push(Violation::OffsetDecreasing {
+                    neuron: neuron as u32,
+                });
+            }
+        }
+        if let Some(&end) = offsets.last()
+            && end as usize != e
+        {
+            report.push(Violation::OffsetEnd { end, synapses: e });
+        }
+
+        // Synapses
+        report.check_len("weight", topology.weight.len(), e);
+        report.check_len("delay", topology.delay.len(), e);
+        report.check_len("in_flight", self.state.in_flight.len(), e);
+        for (synapse, &receiver) in topology.receiver.iter().enumerate() {
+            if receiver as usize >= n {
+                report.push(Violation::ReceiverOutOfRange {
+                    synapse: synapse as u32,
+                    receiver,
+                });
+            }
+        }
+        report.check_finite("weight", &topology.weight);
+        for (synapse, &delay) in topology.delay.iter().enumerate() {
+            if delay == 0 {
+                report.push(Violation::ZeroDelay {
+                    synapse: synapse as u32,
+                });
+            }
+        }
+        let ring = self.state.pending.len();
+        let max_delay = topology.delay.iter().copied().max().unwrap_or(0);
+        if ring <= max_delay as usize {
+            report.push(Violation::RingTooShort {
+                ring,
+                delay: max_delay,
+            });
+        }
+        let mut pending = vec![0u32; e];
+        for &synapse in self.state.pending.iter().flatten() {
+            match pending.get_mut(synapse as usize) {
+                Some(count) => *count += 1,
+                None => report.push(Violation::PendingOutOfRange { synapse }),
+            }
+        }
+        if self.state.in_flight.len() == e {
+            for (synapse, (&in_flight, &pending)) in
+                self.state.in_flight.iter().zip(&pending).enumerate()
+            {
+                if in_flight != pending {
+                    report.push(Violation::InFlightMismatch {
+                        synapse: synapse as u32,
+                        in_flight,
+                        pending,
+                    });
+                }
+            }
+        }
+
+        // Per-neuron arrays
+        report.check_len("spiked", self.state.spiked.len(), n);
+        report.check_len("input", self.state.input.len(), n);
+        report.check_len("bias", self.bias.len(), n);
+        report.check_len("kinds", self.kinds.len(), n);
+        if let Some(coordinates) = &self.coordinates {
+            report.check_len("coordinates", coordinates.len(), n);
+        }
+        for population in &self.populations {
+            if population.range.start > population.range.end {
+                report.push(Violation::PopulationReversed {
+                    name: population.name.clone(),
+                });
+            }
+            if population.range.end as usize > n {
+                report.push(Violation::PopulationOutOfRange {
+                    name: population.name.clone(),
+                });
+            }
+        }
+        if let Some(structure) = &self.structure {
+            report.check_len("neuron_keys", structure.neuron_keys.len(), n);
+            report.check_len("synapse_keys", structure.synapse_keys.len(), e);
+        }
+        report.check_finite("v", self.state.neurons.v());
+        report.check_finite("input", &self.state.input);
+        report.check_finite("bias", &self.bias);
+
+        // Learning state
+        if let Some(plasticity) = &self.plasticity {
+            report.check_len("pre_trace", plasticity.pre_trace.len(), n);
+            report.check_len("post_trace", plasticity.post_trace.len(), n);
+            if let Some(eligibility) = &plasticity.eligibility {
+                report.check_len("eligibility", eligibility.trace.len(), e);
+            }
+        }
+
+        // Stimuli and monitors
+        for (stimulus, s) in self.stimuli.iter().enumerate() {
+            for &neuron in &s.targets {
+                if neuron as usize >= n {
+                    report.push(Violation::StimulusTargetOutOfRange { stimulus, neuron });
+                }
+            }
+            if let Err(Error::InvalidStimulus(problem)) = s.waveform.check() {
+                report.push(Violation::InvalidStimulus { stimulus, problem });
+            }
+        }
+        let spike_watched = self
+            .monitors
+            .spikes
+            .iter()
+            .flatten()
+            .flat_map(|m| m.neurons().unwrap_or_default());
+        let state_watched = self
+            .monitors
+            .states
+            .iter()
+            .flatten()
+            .flat_map(|m| m.neurons());
+        for &neuron in spike_watched.chain(state_watched) {
+            if neuron != REMOVED && neuron as usize >= n {
+                report.push(Violation::MonitorOutOfRange { neuron });
+            }
+        }
+
+        // Neuron parameters, only for the neurons every array covers
+        let mut covered = n;
+        for (array, len) in self.state.neurons.array_lengths() {
+            report.check_len(array, len, n);
+            covered = covered.min(len);
+        }
+        let mut problems = Vec::new();
+        for i in 0..covered {
+            self.state.neurons.check_params(i, &mut problems);
+            for problem in problems.drain(..) {
+                report.push(Violation::Parameter {
+                    neuron: i as u32,
+                    problem,
+                });
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Violation;
+    use crate::{Lif, Model, ModelBuilder, NeuronId, NeuronParams};
+
+    fn ring() -> Model {
+        let mut builder = ModelBuilder::new();
+        builder.add_neurons(4, NeuronParams::default());
+        for i in 0..4 {
+            builder.connect(NeuronId(i), NeuronId((i + 1) % 4), 0.5);
+        }
+        builder.build()
+    }
+
+    /// Broken offsets, lengths and parameters are all reported at once.
+    #[test]
+    fn reports_every_violation() {
+        let mut model = ring();
+        assert!(model.validate().is_valid());
+        model.neurons_mut().set_v_reset(2, 5.0);
+        let topology = model.topology_mut();
+        topology.out_offset[1] = 3;
+        topology.weight.pop();
+        topology.delay[3] = 0;
+        let report = model.validate();
+        for violation in [
+            Violation::OffsetDecreasing { neuron: 1 },
+            Violation::LengthMismatch {
+                array: "weight",
+                len: 3,
+                expected: 4,
+            },
+            Violation::ZeroDelay { synapse: 3 },
+            Violation::Parameter {
+                neuron: 2,
+                problem: "v_reset not below v_th",
+            },
+        ] {
+            assert!(report.violations().contains(&violation), "{report}");
+        }
+        assert_eq!(report.violations().len(), 4, "{report}");
+    }
+
+    /// Neuron arrays of different lengths are reported, not indexed past
+    /// their end.
+    #[test]
+    fn ragged_neurons_do_not_panic() {
+        let mut model = ring();
+        model.state.neurons = ron::from_str::<Lif>(
+            "(v: [0.0, 0.0, 0.0, 0.0], alpha: Uniform(0.1), v_th: [1.0, 0.5], \
+             v_reset: [0.0, 0.5, 0.0], refrac: [0, 0, 0, 0], refrac_len: Each([2]))",
+        )
+        .unwrap();
+        let report = model.validate();
+        for violation in [
+            Violation::LengthMismatch {
+                array: "v_th",
+                len: 2,
+                expected: 4,
+            },
+            Violation::LengthMismatch {
+                array: "v_reset",
+                len: 3,
+                expected: 4,
+            },
+            Violation::LengthMismatch {
+                array: "refrac_len",
+                len: 1,
+                expected: 4,
+            },
+        ] {
+            assert!(report.violations().contains(&violation), "{report}");
+        }
+        assert!(
+            !report
+                .violations()
+                .iter()
+                .any(|v| matches!(v, Violation::Parameter { .. })),
+            "{report}"
+        );
+    }
+}